@@ -0,0 +1,243 @@
+use clap::{Args, Subcommand};
+use std::time::Duration;
+use wireguard_converted_nekoray_rust::candidates::{
+    CandidateFamily, DEFAULT_CANDIDATES, MAX_CANDIDATES,
+};
+use wireguard_converted_nekoray_rust::endpoint::Endpoint;
+use wireguard_converted_nekoray_rust::format::OutputFormat;
+use wireguard_converted_nekoray_rust::naming::NameTemplate;
+use wireguard_converted_nekoray_rust::output::WriteMode;
+use wireguard_converted_nekoray_rust::paths::{
+    CONFIG_ENV, DEFAULT_CANDIDATES_FILE, IPS_ENV, OUTPUT_ENV, PROFILE_ENV, SETTINGS_ENV,
+};
+use wireguard_converted_nekoray_rust::ports::BarePorts;
+use wireguard_converted_nekoray_rust::probe::{
+    ProbeOptions, DEFAULT_ATTEMPTS, DEFAULT_TIMEOUT_MS, DEFAULT_WORKERS,
+};
+use wireguard_converted_nekoray_rust::reserved::Reserved;
+use wireguard_converted_nekoray_rust::singbox::SingBoxTarget;
+use wireguard_converted_nekoray_rust::xray::XrayLayout;
+
+/* 两个程序共用的子命令，app1不带子命令时默认为convert，app2默认为batch */
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// 输入Endpoint端点，生成节点链接并复制到剪贴板
+    Convert(ConvertArgs),
+    /// 批量将ip.txt中的Endpoint生成节点链接或者配置
+    Batch(Box<BatchArgs>),
+    /// 把nekoray链接还原成WireGuard配置
+    Decode(DecodeArgs),
+    /// 向ip.txt中的Endpoint发送WireGuard握手，按照丢包率和延迟排列，写入CSV文件
+    Probe(ProbeArgs),
+    /// 从WARP已知的IP段和端口随机生成候选Endpoint，写入candidates.txt
+    Candidates(CandidatesArgs),
+}
+
+/* convert和batch共用的参数：配置文件、MTU、节点名称前缀、输出格式等 */
+#[derive(Debug, Clone, Args)]
+pub struct NodeArgs {
+    /// WireGuard配置文件(也可以是一行一条的wireguard://链接)，"-"表示从标准输入读取 [默认: wg-config.conf]
+    #[arg(short, long, env = CONFIG_ENV)]
+    pub config: Option<String>,
+    /// TOML格式的设置文件 [默认: 当前目录的wg-nekoray.toml]
+    #[arg(long, env = SETTINGS_ENV)]
+    pub settings: Option<String>,
+    /// 使用设置文件中[profiles.名称]的值(比如hk)
+    #[arg(long, env = PROFILE_ENV)]
+    pub profile: Option<String>,
+    /// MTU值，取值范围为1280~1500，不指定时使用配置文件的值(没有就使用1408)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1280..=1500))]
+    pub mtu: Option<u16>,
+    /// 节点名称或别名的前缀(比如CN)
+    #[arg(short, long)]
+    pub prefix: Option<String>,
+    /// 节点名称模板，可以使用{prefix}、{host}、{port}、{index}、{family}、{latency}、{loss}、{country}、{flag}、{asn}、{mtu}；不指定时节点名称为“前缀_主机地址:端口”，没有前缀时为“主机地址:端口”
+    #[arg(long)]
+    pub name_template: Option<NameTemplate>,
+    /// 输出格式：custom、native、singbox、clash、wireguard、xray、wg-quick、openwrt、networkd
+    #[arg(short, long)]
+    pub format: Option<OutputFormat>,
+    /// 所有节点都使用这个reserved值(比如1,2,3)，优先于配置文件
+    #[arg(long)]
+    pub reserved: Option<Reserved>,
+    /// nekoray自定义核心节点的端口 [默认: 1080]
+    #[arg(long)]
+    pub nekoray_port: Option<u16>,
+    /// nekoray自定义核心节点的socks端口 [默认: 0]
+    #[arg(long)]
+    pub socks_port: Option<u16>,
+    /// 程序结束时不等待按Enter键
+    #[arg(long)]
+    pub no_pause: bool,
+}
+
+/* convert子命令的参数 */
+#[derive(Debug, Clone, Args)]
+pub struct ConvertArgs {
+    #[command(flatten)]
+    pub node: NodeArgs,
+    /// Endpoint端点(比如162.159.192.1:2408)，可以指定多次；不指定时在窗口中循环输入
+    #[arg(short, long)]
+    pub endpoint: Vec<Endpoint>,
+    /// 把生成的链接写入这个文件，而不是复制到剪贴板，"-"表示写到标准输出(不使用环境变量和设置文件中的output)
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// 输出文件已经存在时：overwrite(覆盖)、append(追加)、merge(只添加新的节点)
+    #[arg(short, long, default_value = "overwrite")]
+    pub mode: WriteMode,
+}
+
+/* batch子命令的参数 */
+#[derive(Debug, Clone, Args)]
+pub struct BatchArgs {
+    #[command(flatten)]
+    pub node: NodeArgs,
+    /// 优选的IP或server:port列表文件，"-"表示从标准输入读取 [默认: ip.txt]
+    #[arg(short, long, env = IPS_ENV)]
+    pub ips: Option<String>,
+    /// 输出文件(wg-quick、networkd格式是输出目录)，"-"表示写到标准输出，不指定时根据输出格式决定
+    #[arg(short, long, env = OUTPUT_ENV)]
+    pub output: Option<String>,
+    /// 输出文件已经存在时：overwrite(覆盖)、append(追加)、merge(只添加新的节点)，后两种只支持链接格式
+    #[arg(short, long, default_value = "overwrite")]
+    pub mode: WriteMode,
+    /// ip.txt中只有主机地址的行使用哪些端口：auto(配置文件Endpoint的端口，没有时为2408)、warp(WARP已知的全部端口)或者逗号分隔的端口
+    #[arg(long)]
+    pub ports: Option<BarePorts>,
+    /// 把ip.txt中被跳过的行(和原因)写入这个文件
+    #[arg(long)]
+    pub rejects: Option<String>,
+    /// ip.txt是扫描器的result.csv时，去掉延迟超过这个值(毫秒)的Endpoint
+    #[arg(long, value_name = "MS")]
+    pub max_latency: Option<f64>,
+    /// ip.txt是扫描器的result.csv时，去掉丢包率超过这个值(百分比，0表示不能丢包)的Endpoint
+    #[arg(long, value_name = "PERCENT")]
+    pub max_loss: Option<f64>,
+    /// 每个[Peer]都跟ip.txt中的每个Endpoint组合生成节点
+    #[arg(long)]
+    pub cross: bool,
+    /// 生成节点前先向每个Endpoint发送WireGuard握手，按照丢包率和延迟排列(可以再用--max-latency、--max-loss过滤)
+    #[arg(long)]
+    pub probe: bool,
+    #[command(flatten)]
+    pub probe_settings: ProbeSettings,
+    /// 本地的GeoIP数据库(.mmdb文件，国家或ASN数据库)，可以指定多次；查到的信息用于节点名称和过滤
+    #[arg(long, value_name = "MMDB")]
+    pub geoip: Vec<String>,
+    /// 只保留这些国家或地区的Endpoint(逗号分隔的代码，比如HK,SG)
+    #[arg(long, value_delimiter = ',', requires = "geoip")]
+    pub country: Vec<String>,
+    /// 去掉这些国家或地区的Endpoint(逗号分隔的代码)
+    #[arg(long, value_delimiter = ',', requires = "geoip")]
+    pub exclude_country: Vec<String>,
+    /// 只保留这些自治系统的Endpoint(逗号分隔的编号，比如13335)
+    #[arg(long, value_delimiter = ',', requires = "geoip")]
+    pub asn: Vec<u32>,
+    /// 按照国家或地区拆分输出，每个国家或地区写入一个文件(比如output_HK.txt)或目录
+    #[arg(long, requires = "geoip")]
+    pub split_by_country: bool,
+    /// 输出sing-box配置时，1.11(或endpoint)生成1.11开始的wireguard端点，默认生成wireguard出站
+    #[arg(long)]
+    pub singbox_version: Option<SingBoxTarget>,
+    /// 输出Clash配置时，再生成一个包含所有节点的url-test代理组
+    #[arg(long)]
+    pub clash_group: Option<String>,
+    /// 输出Xray配置时，standalone生成可以直接运行的完整配置，默认只生成出站的数组
+    #[arg(long)]
+    pub xray_layout: Option<XrayLayout>,
+}
+
+/* 发送WireGuard握手测试Endpoint时的参数 */
+#[derive(Debug, Clone, Args)]
+pub struct ProbeSettings {
+    /// 每个Endpoint发送几次握手
+    #[arg(long, default_value_t = DEFAULT_ATTEMPTS, value_parser = clap::value_parser!(u32).range(1..=100))]
+    pub attempts: u32,
+    /// 等待握手响应的时间(毫秒)，取值范围为1~60000
+    #[arg(long, value_name = "MS", default_value_t = DEFAULT_TIMEOUT_MS, value_parser = clap::value_parser!(u64).range(1..=60000))]
+    pub timeout: u64,
+    /// 同时测试几个Endpoint
+    #[arg(long, default_value_t = DEFAULT_WORKERS)]
+    pub workers: usize,
+}
+
+impl ProbeSettings {
+    pub fn options(&self) -> ProbeOptions {
+        ProbeOptions {
+            attempts: self.attempts,
+            timeout: Duration::from_millis(self.timeout),
+            workers: self.workers,
+        }
+    }
+}
+
+/* probe子命令的参数 */
+#[derive(Debug, Clone, Args)]
+pub struct ProbeArgs {
+    /// WireGuard配置文件，使用其中的私钥和第一个[Peer]的公钥发起握手 [默认: wg-config.conf]
+    #[arg(short, long, env = CONFIG_ENV)]
+    pub config: Option<String>,
+    /// TOML格式的设置文件 [默认: 当前目录的wg-nekoray.toml]
+    #[arg(long, env = SETTINGS_ENV)]
+    pub settings: Option<String>,
+    /// 使用设置文件中[profiles.名称]的值(比如hk)
+    #[arg(long, env = PROFILE_ENV)]
+    pub profile: Option<String>,
+    /// 优选的IP或server:port列表文件，"-"表示从标准输入读取 [默认: ip.txt]
+    #[arg(short, long, env = IPS_ENV)]
+    pub ips: Option<String>,
+    /// 测试结果写入这个CSV文件(格式跟warp-yxip的result.csv一样)，"-"表示写到标准输出
+    #[arg(short, long, default_value = "probe.csv")]
+    pub output: String,
+    /// ip.txt中只有主机地址的行使用哪些端口：auto、warp或者逗号分隔的端口
+    #[arg(long)]
+    pub ports: Option<BarePorts>,
+    /// 握手消息中使用这个reserved值(比如1,2,3)，优先于配置文件
+    #[arg(long)]
+    pub reserved: Option<Reserved>,
+    #[command(flatten)]
+    pub probe_settings: ProbeSettings,
+    /// 程序结束时不等待按Enter键
+    #[arg(long)]
+    pub no_pause: bool,
+}
+
+/* candidates子命令的参数 */
+#[derive(Debug, Clone, Args)]
+pub struct CandidatesArgs {
+    /// 最多生成多少个候选Endpoint，取值范围为1~100000
+    #[arg(short = 'n', long, default_value_t = DEFAULT_CANDIDATES, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=MAX_CANDIDATES as u64))]
+    pub count: usize,
+    /// 随机数种子，相同的种子每次生成相同的候选Endpoint
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// 使用哪些IP段：ipv4、ipv6或者all
+    #[arg(long, default_value = "ipv4")]
+    pub family: CandidateFamily,
+    /// 跟地址组合的端口：warp(默认，WARP已知的全部端口)或者逗号分隔的端口
+    #[arg(long)]
+    pub ports: Option<BarePorts>,
+    /// 写入这个文件，"-"表示写到标准输出；作为ip.txt使用时加上--ips candidates.txt
+    #[arg(short, long, default_value = DEFAULT_CANDIDATES_FILE)]
+    pub output: String,
+    /// 输出文件已经存在时：overwrite(覆盖)、append(追加)、merge(只添加新的Endpoint)
+    #[arg(short, long, default_value = "overwrite")]
+    pub mode: WriteMode,
+    /// 程序结束时不等待按Enter键
+    #[arg(long)]
+    pub no_pause: bool,
+}
+
+/* decode子命令的参数 */
+#[derive(Debug, Clone, Args)]
+pub struct DecodeArgs {
+    /// 保存nekoray链接的文件，"-"表示从标准输入读取
+    #[arg(default_value = "output.txt")]
+    pub file: String,
+    /// 每个节点写入这个目录中的一个.conf文件
+    #[arg(short, long)]
+    pub dir: Option<String>,
+    /// 程序结束时不等待按Enter键
+    #[arg(long)]
+    pub no_pause: bool,
+}
@@ -0,0 +1,762 @@
+use crate::app::cli::{
+    BatchArgs, CandidatesArgs, Command, ConvertArgs, DecodeArgs, NodeArgs, ProbeArgs, ProbeSettings,
+};
+use crate::app::console::{
+    check_file_exist_or_zero_size, exit_with_error, is_interactive, name_prefix, read_line,
+    read_mtu, read_prefix, set_no_pause, set_status_to_stderr, status, wait_for_enter,
+};
+use clipboard::{ClipboardContext, ClipboardProvider};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+use wireguard_converted_nekoray_rust::candidates::{warp_candidates, CandidateOptions};
+use wireguard_converted_nekoray_rust::clash::generate_clash_config;
+use wireguard_converted_nekoray_rust::config::WireGuardConfig;
+use wireguard_converted_nekoray_rust::endpoint::{parse_endpoint_lines, Endpoint, EndpointList};
+use wireguard_converted_nekoray_rust::error::Error;
+use wireguard_converted_nekoray_rust::format::OutputFormat;
+use wireguard_converted_nekoray_rust::geoip::{
+    group_by_country, GeoFilter, GeoInfo, GeoIpDatabase,
+};
+use wireguard_converted_nekoray_rust::naming::NodeNaming;
+use wireguard_converted_nekoray_rust::nekoray::{decode_nekoray_link, generate_nekoray_node};
+use wireguard_converted_nekoray_rust::networkd::{generate_networkd_files, is_networkd_file};
+use wireguard_converted_nekoray_rust::node::{file_safe_name, plan_nodes, NodeOptions};
+use wireguard_converted_nekoray_rust::openwrt::generate_openwrt_config;
+use wireguard_converted_nekoray_rust::output::{write_files, write_lines, WriteMode};
+use wireguard_converted_nekoray_rust::paths::{
+    is_stdio, read_input, DEFAULT_CONFIG_FILE, DEFAULT_IPS_FILE,
+};
+use wireguard_converted_nekoray_rust::ports::WARP_PORTS;
+use wireguard_converted_nekoray_rust::probe::{
+    probe_endpoints, rank_results, ProbeKeys, ProbeResult,
+};
+use wireguard_converted_nekoray_rust::settings::Settings;
+use wireguard_converted_nekoray_rust::singbox::generate_singbox_config;
+use wireguard_converted_nekoray_rust::wg_quick::{generate_wg_quick_files, is_wg_quick_file};
+use wireguard_converted_nekoray_rust::wireguard_uri::generate_wireguard_uris;
+use wireguard_converted_nekoray_rust::xray::generate_xray_config;
+
+/* 执行子命令 */
+pub fn run(command: Command) -> io::Result<()> {
+    match command {
+        Command::Convert(args) => run_convert(args),
+        Command::Batch(args) => run_batch(*args),
+        Command::Decode(args) => run_decode(args),
+        Command::Probe(args) => run_probe(args),
+        Command::Candidates(args) => run_candidates(args),
+    }
+}
+
+/* 读取WireGuard配置文件("-"表示标准输入)，读取失败时提示用户并退出程序 */
+fn load_config(file: &str) -> WireGuardConfig {
+    let contents = read_input(file).unwrap_or_else(|err| exit_with_error(Error::from(err)));
+    WireGuardConfig::from_contents(&contents).unwrap_or_else(|err| exit_with_error(err))
+}
+
+/* 读取设置文件，再用命令行参数(和环境变量)覆盖设置文件中的值；格式不正确时提示用户并退出程序 */
+fn load_settings(args: &NodeArgs, ips: Option<&str>, output: Option<&str>) -> Settings {
+    let settings = Settings::load(args.settings.as_deref(), args.profile.as_deref())
+        .unwrap_or_else(|err| exit_with_error(err));
+    settings.merge(Settings {
+        config: args.config.clone(),
+        ips: ips.map(str::to_string),
+        output: output.map(str::to_string),
+        mtu: args.mtu,
+        prefix: args.prefix.clone(),
+        name_template: args.name_template.clone(),
+        format: args.format,
+        reserved: args.reserved,
+        nekoray_port: args.nekoray_port,
+        socks_port: args.socks_port,
+        ports: None,
+        profiles: BTreeMap::new(),
+    })
+}
+
+/* 提示信息中的输出位置，"-"显示为标准输出 */
+fn written_to(path: &str) -> String {
+    if is_stdio(path) {
+        "已经写到标准输出".to_string()
+    } else {
+        format!("已经写入{}中", path)
+    }
+}
+
+/* 写入输出文件并显示结果 */
+fn write_output(
+    output_file: &str,
+    lines: &[String],
+    mode: WriteMode,
+    format: OutputFormat,
+) -> io::Result<()> {
+    let summary = write_lines(output_file, lines, mode)?;
+    if summary.skipped > 0 {
+        status(format!(
+            "输出文件中已经有{}个相同的节点，跳过；新增的{}个{}{}！",
+            summary.skipped,
+            summary.written,
+            format.label(),
+            written_to(output_file)
+        ));
+    } else {
+        status(format!(
+            "生成的{}{}！",
+            format.label(),
+            written_to(output_file)
+        ));
+    }
+    Ok(())
+}
+
+/* 命令行和设置文件都没有指定MTU值时，在终端窗口中询问 */
+fn node_options(settings: &Settings, format: OutputFormat) -> NodeOptions {
+    NodeOptions {
+        mtu: settings
+            .mtu
+            .or_else(|| if is_interactive() { read_mtu() } else { None }),
+        reserved: settings.reserved,
+        nekoray_format: format.nekoray_format().unwrap_or_default(),
+        nekoray_port: settings.nekoray_port,
+        socks_port: settings.socks_port,
+    }
+}
+
+/* 命令行和设置文件都没有指定节点名称的前缀时，在终端窗口中询问(名称模板中没有{prefix}时不询问) */
+fn prefix_for(settings: &Settings) -> String {
+    let uses_prefix = settings
+        .name_template
+        .as_ref()
+        .is_none_or(|template| template.uses("prefix"));
+    match &settings.prefix {
+        Some(prefix) => name_prefix(prefix),
+        None if uses_prefix && is_interactive() => read_prefix(),
+        None => String::new(),
+    }
+}
+
+/* 节点名称的前缀和模板 */
+fn naming_for(settings: &Settings) -> NodeNaming {
+    NodeNaming {
+        template: settings.name_template.clone(),
+        ..NodeNaming::new(&prefix_for(settings))
+    }
+}
+
+/* 配置文件有多个[Peer]时，每个[Peer]都跟输入的Endpoint组合生成一个节点 */
+fn render_links(
+    config: &WireGuardConfig,
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    format: OutputFormat,
+    options: &NodeOptions,
+) -> Vec<String> {
+    plan_nodes(config, endpoints, true, naming, options)
+        .iter()
+        .filter_map(|spec| format.render_link(config, spec, options))
+        .collect()
+}
+
+/* 把生成的链接复制到剪贴板，复制失败时(比如没有图形界面)只提示，不退出程序 */
+fn copy_to_clipboard(links: &str, format: OutputFormat) {
+    let copied = ClipboardProvider::new()
+        .and_then(|mut clipboard: ClipboardContext| clipboard.set_contents(links.to_string()));
+    status(format!("{:-<120}", ""));
+    match copied {
+        Err(err) => status(format!("\n无法复制到剪切板：{}\n", err)),
+        Ok(()) => {
+            let hint = match format {
+                OutputFormat::Nekoray => "可以黏贴到NekoBox软件中使用！记得要切换为sing-box核心。",
+                OutputFormat::Nekobox => "可以黏贴到新版NekoBox软件中使用！",
+                _ => "可以黏贴到支持这种链接的客户端中使用！",
+            };
+            status(format!(
+                "\n生成的{}已复制到剪切板，{}\n",
+                format.label(),
+                hint
+            ))
+        }
+    }
+}
+
+/* convert：以WireGuard配置文件的参数为基准，把输入的Endpoint生成节点链接 */
+pub fn run_convert(args: ConvertArgs) -> io::Result<()> {
+    set_no_pause(args.node.no_pause);
+    let settings = load_settings(&args.node, None, None);
+    let config_file = settings
+        .config
+        .clone()
+        .unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+    // 环境变量和设置文件中的output是batch的输出文件，convert只在命令行指定了--output时才写入文件
+    let output_file = args.output.clone();
+    set_status_to_stderr(output_file.as_deref().is_some_and(is_stdio));
+    check_file_exist_or_zero_size(&[&config_file]);
+    status("本程序的用途：以WireGuard配置文件的参数为基准，生成NekoRay链接。\n");
+    let config = load_config(&config_file);
+    let format = settings.format.unwrap_or_default();
+    if !format.is_link() {
+        exit_with_error(format!(
+            "convert只能生成链接，{}请使用batch子命令生成。",
+            format.label()
+        ));
+    }
+    let options = node_options(&settings, format);
+
+    // 命令行指定了Endpoint时，只生成一次，不进入循环
+    if !args.endpoint.is_empty() {
+        let naming = naming_for(&settings);
+        let links = render_links(&config, &args.endpoint, &naming, format, &options);
+        match &output_file {
+            Some(output_file) => write_output(output_file, &links, args.mode, format)?,
+            None => {
+                let links = links.join("\n");
+                println!("{}", links);
+                copy_to_clipboard(&links, format);
+            }
+        }
+        return Ok(());
+    }
+    if !is_interactive() {
+        exit_with_error(
+            "没有指定Endpoint端点，请加上--endpoint参数(比如--endpoint 162.159.192.1:2408)",
+        );
+    }
+
+    status(format!("\n{:+<120}", ""));
+    loop {
+        /* 输入的值是ipv4、ipv6、域名，而且端口是合法的就跳出死循环 */
+        let endpoint = loop {
+            let input_endpoint =
+                read_line("\n输入Endpoint端点(主机地址:端口号，比如162.159.192.1:2408)：");
+            if let Some(endpoint) = Endpoint::parse(&input_endpoint) {
+                break endpoint;
+            }
+        };
+
+        let naming = naming_for(&settings);
+        let nekoray_node = render_links(&config, &[endpoint], &naming, format, &options).join("\n");
+
+        status(format!("\n{:-<52}NekoRay节点如下:{:-<52}", "", ""));
+        status(&nekoray_node);
+        // 复制到剪贴板
+        copy_to_clipboard(&nekoray_node, format);
+        status(format!("{:+<120}", ""));
+    }
+}
+
+/* 显示ip.txt中每个被跳过的行和原因，最后显示统计；指定了rejects文件时，把被跳过的行写入这个文件 */
+fn report_rejected(
+    list: &EndpointList,
+    ips_file: &str,
+    rejects_file: Option<&str>,
+) -> io::Result<()> {
+    for rejected in &list.rejected {
+        status(format!("{}{}，跳过", ips_file, rejected));
+    }
+    // 没有被跳过的行时同样覆盖，不留下上一次运行的内容
+    if let Some(rejects_file) = rejects_file {
+        // 每行保留原来的内容，原因写在行尾的注释中，修改后可以直接放回ip.txt
+        let lines: Vec<String> = list
+            .rejected
+            .iter()
+            .map(|rejected| {
+                format!(
+                    "{} # 第{}行：{}",
+                    rejected.text, rejected.line, rejected.reason
+                )
+            })
+            .collect();
+        write_lines(rejects_file, &lines, WriteMode::Overwrite)?;
+        if !lines.is_empty() {
+            status(format!("被跳过的行{}。\n", written_to(rejects_file)));
+        }
+    }
+    Ok(())
+}
+
+/* 解析ip.txt的统计：可以使用的Endpoint个数，每种原因跳过了几行，在程序结束前显示 */
+fn reject_summary(list: &EndpointList, ips_file: &str) -> String {
+    if list.rejected.is_empty() {
+        return format!(
+            "{}中有{}个可以使用的Endpoint，没有跳过任何行。",
+            ips_file,
+            list.endpoints.len()
+        );
+    }
+    let counts: Vec<String> = list
+        .reject_counts()
+        .iter()
+        .map(|(label, count)| format!("{}{}行", label, count))
+        .collect();
+    format!(
+        "{}中有{}个可以使用的Endpoint，跳过了{}行（{}）。",
+        ips_file,
+        list.endpoints.len(),
+        list.rejected.len(),
+        counts.join("、")
+    )
+}
+
+/* 使用配置文件的私钥和第一个[Peer]的公钥、reserved发起握手，密钥不合法时提示用户并退出程序 */
+fn probe_keys(config: &WireGuardConfig, settings: &Settings) -> ProbeKeys {
+    let options = NodeOptions {
+        reserved: settings.reserved,
+        ..NodeOptions::default()
+    };
+    ProbeKeys::from_config(config, &options).unwrap_or_else(|err| exit_with_error(err))
+}
+
+/* 一个Endpoint的测试结果 */
+fn describe_probe(result: &ProbeResult) -> String {
+    match result.latency() {
+        Some(latency) => format!(
+            "{} => 丢包率{:.0}%，平均延迟{:.0}毫秒",
+            result.endpoint,
+            result.loss(),
+            latency
+        ),
+        None => format!("{} => 没有握手响应", result.endpoint),
+    }
+}
+
+/* 向每个Endpoint发送WireGuard握手，按照丢包率和延迟排列测试结果 */
+fn probe_and_rank(
+    endpoints: &[Endpoint],
+    keys: &ProbeKeys,
+    settings: &ProbeSettings,
+) -> Vec<ProbeResult> {
+    let options = settings.options();
+    status(format!(
+        "正在向{}个Endpoint发送WireGuard握手（每个{}次，等待{}毫秒）……",
+        endpoints.len(),
+        options.attempts,
+        settings.timeout
+    ));
+    let mut results = probe_endpoints(endpoints, keys, &options, |result| {
+        status(describe_probe(result))
+    });
+    rank_results(&mut results);
+    let reachable = results.iter().filter(|result| result.received > 0).count();
+    status(format!(
+        "{}个Endpoint中有{}个收到了握手响应。\n",
+        results.len(),
+        reachable
+    ));
+    results
+}
+
+/* 指定了GeoIP数据库时，查询每个Endpoint的地理信息，再按照国家或地区、ASN过滤 */
+fn annotate_geoip(args: &BatchArgs, endpoints: &mut Vec<Endpoint>) -> HashMap<Endpoint, GeoInfo> {
+    if args.geoip.is_empty() {
+        return HashMap::new();
+    }
+    let database = GeoIpDatabase::open(&args.geoip).unwrap_or_else(|err| exit_with_error(err));
+    let geo = database.annotate(endpoints);
+    status(format!(
+        "GeoIP数据库中查到了{}个Endpoint的地理信息（共{}个）。",
+        geo.len(),
+        endpoints.len()
+    ));
+    let filter = GeoFilter {
+        countries: args.country.clone(),
+        exclude_countries: args.exclude_country.clone(),
+        asns: args.asn.clone(),
+    };
+    if !filter.is_empty() {
+        let removed = filter.apply(endpoints, &geo);
+        status(format!(
+            "按照国家或地区、ASN去掉了{}个Endpoint，剩下{}个。",
+            removed,
+            endpoints.len()
+        ));
+    }
+    geo
+}
+
+/* batch：以WireGuard配置文件的参数为基准，批量将ip.txt中的Endpoint生成节点链接或者配置 */
+pub fn run_batch(args: BatchArgs) -> io::Result<()> {
+    set_no_pause(args.node.no_pause);
+    let settings = load_settings(&args.node, args.ips.as_deref(), args.output.as_deref());
+    let config_file = settings
+        .config
+        .clone()
+        .unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+    let ips_file = settings
+        .ips
+        .clone()
+        .unwrap_or_else(|| DEFAULT_IPS_FILE.to_string());
+    let format = settings.format.unwrap_or_default();
+    let output_file = settings
+        .output
+        .clone()
+        .unwrap_or_else(|| format.default_output_file().to_string());
+    set_status_to_stderr(is_stdio(&output_file));
+    // 追加和合并只适用于一行一条链接的格式，JSON、YAML等配置追加后就不是合法的文件了
+    if args.mode != WriteMode::Overwrite && !format.is_link() {
+        exit_with_error(format!(
+            "{}只能覆盖输出文件，不支持--mode append或merge",
+            format.label()
+        ));
+    }
+    if is_stdio(&config_file) && is_stdio(&ips_file) {
+        exit_with_error("配置文件和ip.txt不能都从标准输入读取");
+    }
+    check_file_exist_or_zero_size(&[&config_file]);
+    status("本程序的用途：以WireGuard配置文件的参数为基准，批量生成NekoRay链接。\n");
+    let config = load_config(&config_file);
+    let cross = args.cross;
+    // 配置文件有多个[Peer]时，ip.txt可以不存在，这时每个[Peer]使用自己的Endpoint
+    let uses_ips = config.peers.len() == 1 || cross;
+    let content = if !uses_ips {
+        status(format!(
+            "配置文件中有{}个[Peer]，每个[Peer]使用自己的Endpoint生成节点（加上--cross参数才使用{}中的Endpoint）。\n",
+            config.peers.len(),
+            ips_file
+        ));
+        String::new()
+    } else {
+        check_file_exist_or_zero_size(&[&ips_file]);
+        read_input(&ips_file)?
+    };
+    let bare_ports = args
+        .ports
+        .clone()
+        .or_else(|| settings.ports.clone())
+        .unwrap_or_default()
+        .resolve(&config);
+    let mut endpoint_list = parse_endpoint_lines(&content, &bare_ports);
+    report_rejected(&endpoint_list, &ips_file, args.rejects.as_deref())?;
+    let summary = uses_ips.then(|| reject_summary(&endpoint_list, &ips_file));
+    let parsed = endpoint_list.endpoints.len();
+    // 用握手测试的结果代替扫描器的测量值，Endpoint按照测试结果重新排列
+    if args.probe && !endpoint_list.endpoints.is_empty() {
+        let keys = probe_keys(&config, &settings);
+        let results = probe_and_rank(&endpoint_list.endpoints, &keys, &args.probe_settings);
+        endpoint_list.endpoints = results
+            .iter()
+            .map(|result| result.endpoint.clone())
+            .collect();
+        for result in &results {
+            endpoint_list
+                .metrics
+                .insert(result.endpoint.clone(), result.metrics());
+        }
+    }
+    if args.max_latency.is_some() || args.max_loss.is_some() {
+        let removed = endpoint_list.filter_by_metrics(args.max_latency, args.max_loss);
+        status(format!(
+            "按照延迟和丢包率的上限去掉了{}个Endpoint，剩下{}个。\n",
+            removed,
+            endpoint_list.endpoints.len()
+        ));
+    }
+    let geo = annotate_geoip(&args, &mut endpoint_list.endpoints);
+    // 全部被过滤掉时不能退回到配置文件中的Endpoint
+    if parsed > 0 && endpoint_list.endpoints.is_empty() {
+        exit_with_error(format!("{}中没有符合条件的Endpoint", ips_file));
+    }
+    let endpoints = &endpoint_list.endpoints;
+    let options = node_options(&settings, format);
+
+    status(format!("\n{:-<85}", ""));
+    let naming = NodeNaming {
+        metrics: endpoint_list.metrics.clone(),
+        geo,
+        ..naming_for(&settings)
+    };
+    status(format!("{:-<85}", ""));
+
+    let outputs = if args.split_by_country && !endpoints.is_empty() {
+        if is_stdio(&output_file) {
+            exit_with_error("按照国家或地区拆分输出时，不能写到标准输出");
+        }
+        group_by_country(endpoints, &naming.geo)
+            .into_iter()
+            .map(|(country, endpoints)| (country_output_file(&output_file, &country), endpoints))
+            .collect()
+    } else {
+        vec![(output_file.clone(), endpoints.clone())]
+    };
+    for (output_file, endpoints) in &outputs {
+        write_batch_output(
+            &args,
+            format,
+            endpoints,
+            &naming,
+            &config,
+            &options,
+            output_file,
+        )?;
+    }
+    if let Some(summary) = summary {
+        status(format!("\n{}", summary));
+    }
+
+    wait_for_enter();
+
+    Ok(())
+}
+
+/* 按照国家或地区拆分输出时的文件名：在扩展名前面加上"_国家或地区代码"(比如output_HK.txt、wg-quick_HK) */
+fn country_output_file(output_file: &str, country: &str) -> String {
+    let path = Path::new(output_file);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, country, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, country),
+    };
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+/* 按照输出格式批量生成节点链接或者配置，写入输出文件(wg-quick、networkd格式写入输出目录) */
+fn write_batch_output(
+    args: &BatchArgs,
+    format: OutputFormat,
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    output_file: &str,
+) -> io::Result<()> {
+    let cross = args.cross;
+    let singbox_target = args.singbox_version.unwrap_or_default();
+    let xray_layout = args.xray_layout.unwrap_or_default();
+    // 每生成一个节点显示一行提示
+    let progress =
+        |name: &str, generated: &str| status(format!("{} => {}已生成！", name, generated));
+    // wg-quick、networkd格式每个节点生成单独的配置文件，写入输出目录(默认为wg-quick、networkd目录)
+    let output_files = match format {
+        OutputFormat::WgQuick => Some(generate_wg_quick_files(
+            endpoints, naming, config, options, cross, progress,
+        )),
+        OutputFormat::Networkd => Some(generate_networkd_files(
+            endpoints, naming, config, options, cross, progress,
+        )),
+        _ => None,
+    };
+    if let Some(files) = output_files {
+        status(format!("{:-<85}", ""));
+        if files.is_empty() {
+            status(format!("没有生成任何{}！", format.label()));
+        } else if is_stdio(output_file) {
+            exit_with_error(format!(
+                "{}要写入输出目录，不能写到标准输出",
+                format.label()
+            ));
+        } else {
+            // 只删除这种格式以前生成的文件，目录中的其他文件不动
+            let generated = |file_name: &str| match format {
+                OutputFormat::WgQuick => is_wg_quick_file(&naming.prefix, file_name),
+                _ => is_networkd_file(file_name),
+            };
+            write_files(output_file, &files, generated)?;
+            status(format!(
+                "生成的{}个{}已经写入{}目录中！",
+                files.len(),
+                format.label(),
+                output_file
+            ));
+        }
+        return Ok(());
+    }
+
+    // 按照输出格式批量生成链接或者配置
+    let output_lines: Vec<String> = match format {
+        OutputFormat::Nekoray | OutputFormat::Nekobox => {
+            generate_nekoray_node(endpoints, naming, config, options, cross, progress)
+        }
+        OutputFormat::SingBox => generate_singbox_config(
+            endpoints,
+            naming,
+            config,
+            options,
+            cross,
+            singbox_target,
+            progress,
+        )
+        .into_iter()
+        .collect(),
+        OutputFormat::WireGuardUri => {
+            generate_wireguard_uris(endpoints, naming, config, options, cross, progress)
+        }
+        OutputFormat::Xray => generate_xray_config(
+            endpoints,
+            naming,
+            config,
+            options,
+            cross,
+            xray_layout,
+            progress,
+        )
+        .into_iter()
+        .collect(),
+        // 已经在上面写入输出目录
+        OutputFormat::WgQuick | OutputFormat::Networkd => Vec::new(),
+        OutputFormat::OpenWrt => {
+            generate_openwrt_config(endpoints, naming, config, options, cross, progress)
+                .into_iter()
+                .collect()
+        }
+        OutputFormat::Clash => generate_clash_config(
+            endpoints,
+            naming,
+            config,
+            options,
+            cross,
+            args.clash_group.as_deref(),
+            progress,
+        )
+        .into_iter()
+        .collect(),
+    };
+    // 检查是否生成了内容
+    if !output_lines.is_empty() {
+        status(format!("{:-<85}", ""));
+        write_output(output_file, &output_lines, args.mode, format)?;
+    } else {
+        status(format!("没有生成任何{}！", format.label()));
+    }
+
+    Ok(())
+}
+
+/* probe：向ip.txt中的Endpoint发送WireGuard握手，按照丢包率和延迟排列，写入CSV文件 */
+pub fn run_probe(args: ProbeArgs) -> io::Result<()> {
+    set_no_pause(args.no_pause);
+    let settings = Settings::load(args.settings.as_deref(), args.profile.as_deref())
+        .unwrap_or_else(|err| exit_with_error(err))
+        .merge(Settings {
+            config: args.config.clone(),
+            ips: args.ips.clone(),
+            reserved: args.reserved,
+            ..Settings::default()
+        });
+    let config_file = settings
+        .config
+        .clone()
+        .unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+    let ips_file = settings
+        .ips
+        .clone()
+        .unwrap_or_else(|| DEFAULT_IPS_FILE.to_string());
+    set_status_to_stderr(is_stdio(&args.output));
+    if is_stdio(&config_file) && is_stdio(&ips_file) {
+        exit_with_error("配置文件和ip.txt不能都从标准输入读取");
+    }
+    check_file_exist_or_zero_size(&[&config_file, &ips_file]);
+    status("本程序的用途：向ip.txt中的Endpoint发送WireGuard握手，测试哪些Endpoint可以连接。\n");
+    let config = load_config(&config_file);
+    let keys = probe_keys(&config, &settings);
+    let bare_ports = args
+        .ports
+        .clone()
+        .or_else(|| settings.ports.clone())
+        .unwrap_or_default()
+        .resolve(&config);
+    let endpoint_list = parse_endpoint_lines(&read_input(&ips_file)?, &bare_ports);
+    report_rejected(&endpoint_list, &ips_file, None)?;
+    let summary = reject_summary(&endpoint_list, &ips_file);
+    if endpoint_list.endpoints.is_empty() {
+        exit_with_error(format!("{}中没有可以测试的Endpoint", ips_file));
+    }
+
+    let results = probe_and_rank(&endpoint_list.endpoints, &keys, &args.probe_settings);
+    // 跟warp-yxip的result.csv格式一样，可以直接作为batch的ip.txt使用
+    let mut lines = vec!["IP:端口,丢包率,延迟".to_string()];
+    lines.extend(results.iter().map(|result| {
+        format!(
+            "{},{:.2}%,{}",
+            result.endpoint,
+            result.loss(),
+            result
+                .latency()
+                .map(|latency| format!("{:.0} ms", latency))
+                .unwrap_or_default()
+        )
+    }));
+    write_lines(&args.output, &lines, WriteMode::Overwrite)?;
+    status(format!("测试结果{}！", written_to(&args.output)));
+    status(format!("\n{}", summary));
+    wait_for_enter();
+    Ok(())
+}
+
+/* candidates：从WARP已知的IP段和端口随机生成候选Endpoint，写入candidates.txt */
+pub fn run_candidates(args: CandidatesArgs) -> io::Result<()> {
+    set_no_pause(args.no_pause);
+    set_status_to_stderr(is_stdio(&args.output));
+    let options = CandidateOptions {
+        family: args.family,
+        // 没有配置文件，auto也使用默认端口
+        ports: args
+            .ports
+            .map(|ports| ports.resolve(&WireGuardConfig::default()))
+            .unwrap_or_else(|| WARP_PORTS.to_vec()),
+        count: args.count,
+        seed: args.seed,
+    };
+    let lines: Vec<String> = warp_candidates(&options)
+        .iter()
+        .map(Endpoint::to_string)
+        .collect();
+    let summary = write_lines(&args.output, &lines, args.mode)?;
+    status(format!(
+        "从{}个IP段、{}个端口中生成了{}个候选Endpoint，其中{}个{}！",
+        options.family.ranges().len(),
+        options.ports.len(),
+        lines.len(),
+        summary.written,
+        written_to(&args.output)
+    ));
+    if !is_stdio(&args.output) {
+        status(format!(
+            "可以使用“probe --ips {0}”测试哪些Endpoint可以连接，或者“batch --ips {0}”直接批量生成节点。",
+            args.output
+        ));
+    }
+    wait_for_enter();
+    Ok(())
+}
+
+/* decode：把文件中的nekoray链接还原成WireGuard配置，输出到屏幕上；指定了目录时每个节点写入一个.conf文件 */
+pub fn run_decode(args: DecodeArgs) -> io::Result<()> {
+    set_no_pause(args.no_pause);
+    let file = args.file.as_str();
+    check_file_exist_or_zero_size(&[file]);
+    let content = read_input(file)?;
+    // 配置文件中有私钥，全部还原以后再跟batch一样用write_files写入(先写临时文件再改名)
+    let mut files: Vec<(String, String)> = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match decode_nekoray_link(line) {
+            Ok(node) => {
+                println!("{:-<85}", "");
+                println!(
+                    "# {}\n# Endpoint = {}\n{}",
+                    node.name, node.endpoint, node.config
+                );
+                if args.dir.is_some() {
+                    let file_name = format!("{}_{}.conf", idx + 1, file_safe_name(&node.name));
+                    files.push((file_name, node.config.to_string()));
+                }
+            }
+            Err(err) => println!("第{}行：{}", idx + 1, err),
+        }
+    }
+    if let Some(dir) = &args.dir {
+        // 只写入这次还原的配置，目录中已有的其他文件不删除
+        write_files(dir, &files, |_| false)?;
+        println!("{:-<85}", "");
+        for (file_name, _) in &files {
+            println!("已写入{}", Path::new(dir).join(file_name).display());
+        }
+    }
+    wait_for_enter();
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use wireguard_converted_nekoray_rust::paths::is_stdio;
+
+// 输出写到标准输出(-)时，提示信息改为写到标准错误，避免跟输出的内容混在一起
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+// 命令行加上--no-pause参数时，程序结束不再等待按Enter键
+static NO_PAUSE: AtomicBool = AtomicBool::new(false);
+
+/* 设置提示信息是否写到标准错误 */
+pub fn set_status_to_stderr(to_stderr: bool) {
+    STATUS_TO_STDERR.store(to_stderr, Ordering::Relaxed);
+}
+
+/* 显示一行提示信息 */
+pub fn status(message: impl fmt::Display) {
+    if STATUS_TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/* 显示输入提示(不换行) */
+fn prompt(message: &str) {
+    if STATUS_TO_STDERR.load(Ordering::Relaxed) {
+        eprint!("{}", message);
+        io::stderr().flush().expect("无法刷新标准错误缓冲区");
+    } else {
+        print!("{}", message);
+        io::stdout().flush().expect("无法刷新标准输出缓冲区");
+    }
+}
+
+/* 检查文件是否缺少或大小为空，缺少时提示用户并退出程序("-"表示标准输入，不检查) */
+pub fn check_file_exist_or_zero_size(files: &[&str]) {
+    for file in files.iter().filter(|file| !is_stdio(file)) {
+        let missing = match fs::metadata(file) {
+            Ok(metadata) => metadata.len() == 0,
+            Err(_) => true,
+        };
+        if missing {
+            exit_with_error(format!(
+                "找不到当前目录的{}文件，是否把文件的位置放错了？",
+                file
+            ));
+        }
+    }
+}
+
+/* 标准输入是终端窗口时才显示输入提示，在脚本、CI或cron中运行时不会卡在等待输入 */
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/* 设置程序结束时是否等待按Enter键 */
+pub fn set_no_pause(no_pause: bool) {
+    NO_PAUSE.store(no_pause, Ordering::Relaxed);
+}
+
+/* 辅助函数：等待用户按Enter键（加上--no-pause参数或者不是在终端窗口中运行时直接返回） */
+pub fn wait_for_enter() {
+    if NO_PAUSE.load(Ordering::Relaxed) || !is_interactive() {
+        return;
+    }
+    prompt("\n按Enter键退出程序 >>");
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("无法读取行");
+}
+
+/* 读取一行用户输入，移除首尾的空白字符 */
+pub fn read_line(prompt: &str) -> String {
+    self::prompt(prompt);
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("无法读取输入");
+    input.trim().to_string()
+}
+
+/* 询问是否修改MTU值，输入为空时返回None（使用配置文件的值） */
+pub fn read_mtu() -> Option<u16> {
+    status(
+        "是否修改MTU值？输入内容为空时，就默认为配置文件的值，配置文件中没有MTU值，就使用1408；",
+    );
+    loop {
+        let input_mtu = read_line("这里输入MTU值，取值范围为1280~1500：");
+        if input_mtu.is_empty() {
+            return None;
+        }
+        if let Ok(parsed) = input_mtu.parse::<u16>() {
+            if (1280..=1500).contains(&parsed) {
+                return Some(parsed);
+            }
+        }
+    }
+}
+
+/* 询问节点名称的前缀，不为空时在后面添加"_" */
+pub fn read_prefix() -> String {
+    name_prefix(&read_line("添加节点名称或别名的前缀吗？(比如，CN)："))
+}
+
+/* 节点名称的前缀，不为空时在后面添加"_" */
+pub fn name_prefix(input: &str) -> String {
+    let input = input.trim();
+    if !input.is_empty() {
+        format!("{}_", input)
+    } else {
+        String::new()
+    }
+}
+
+/* 显示错误信息，等待用户按Enter键后退出程序 */
+pub fn exit_with_error(message: impl fmt::Display) -> ! {
+    status(message);
+    wait_for_enter();
+    std::process::exit(1);
+}
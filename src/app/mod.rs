@@ -0,0 +1,4 @@
+/* app1和app2共用的命令行部分：参数定义、子命令的实现、终端窗口中的交互和剪贴板，不属于库的接口 */
+pub mod cli;
+pub mod commands;
+pub mod console;
@@ -0,0 +1,120 @@
+use crate::endpoint::Endpoint;
+use crate::ip_range::IpRange;
+use crate::ports::WARP_PORTS;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/* WARP已知的IPv4、IPv6网段 */
+pub const WARP_IPV4_RANGES: [&str; 7] = [
+    "162.159.192.0/24",
+    "162.159.193.0/24",
+    "162.159.195.0/24",
+    "188.114.96.0/24",
+    "188.114.97.0/24",
+    "188.114.98.0/24",
+    "188.114.99.0/24",
+];
+pub const WARP_IPV6_RANGES: [&str; 2] = ["2606:4700:d0::/48", "2606:4700:d1::/48"];
+
+/* 默认生成多少个候选Endpoint，一次最多生成多少个 */
+pub const DEFAULT_CANDIDATES: usize = 100;
+pub const MAX_CANDIDATES: usize = 100_000;
+
+/* 从哪些网段生成候选Endpoint */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CandidateFamily {
+    #[default]
+    IPv4,
+    IPv6,
+    All,
+}
+
+impl CandidateFamily {
+    /* 使用的网段 */
+    pub fn ranges(&self) -> Vec<&'static str> {
+        match self {
+            CandidateFamily::IPv4 => WARP_IPV4_RANGES.to_vec(),
+            CandidateFamily::IPv6 => WARP_IPV6_RANGES.to_vec(),
+            CandidateFamily::All => [&WARP_IPV4_RANGES[..], &WARP_IPV6_RANGES[..]].concat(),
+        }
+    }
+}
+
+impl FromStr for CandidateFamily {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ipv4" | "v4" | "4" => Ok(CandidateFamily::IPv4),
+            "ipv6" | "v6" | "6" => Ok(CandidateFamily::IPv6),
+            "all" | "both" => Ok(CandidateFamily::All),
+            _ => Err(format!("“{}”不合法，可选的值：ipv4、ipv6、all", s)),
+        }
+    }
+}
+
+/*
+生成候选Endpoint的参数：
+    family：使用哪些网段；ports：跟地址组合的端口；count：最多生成几个(超过MAX_CANDIDATES时按MAX_CANDIDATES计算)；
+    seed：随机数种子，相同的种子和参数每次生成相同的候选Endpoint，为None时每次都不一样
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateOptions {
+    pub family: CandidateFamily,
+    pub ports: Vec<u16>,
+    pub count: usize,
+    pub seed: Option<u64>,
+}
+
+impl Default for CandidateOptions {
+    fn default() -> Self {
+        CandidateOptions {
+            family: CandidateFamily::default(),
+            ports: WARP_PORTS.to_vec(),
+            count: DEFAULT_CANDIDATES,
+            seed: None,
+        }
+    }
+}
+
+/*
+从WARP已知的网段和端口中随机抽取不重复的候选Endpoint：
+    每次先随机选一个网段(每个网段的机会相同)，再从中随机选一个地址和一个端口；
+    网段和端口的组合不足count个时(只使用IPv4网段)全部返回，按照抽取的顺序排列。
+*/
+pub fn warp_candidates(options: &CandidateOptions) -> Vec<Endpoint> {
+    let ranges: Vec<IpRange> = options
+        .family
+        .ranges()
+        .into_iter()
+        .filter_map(|range| IpRange::parse(range).and_then(Result::ok))
+        .collect();
+    if ranges.is_empty() || options.ports.is_empty() {
+        return Vec::new();
+    }
+    let total = ranges
+        .iter()
+        .map(IpRange::len)
+        .fold(0u128, u128::saturating_add)
+        .saturating_mul(options.ports.len() as u128);
+    let wanted = (options.count.min(MAX_CANDIDATES) as u128).min(total) as usize;
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut seen = HashSet::with_capacity(wanted);
+    let mut candidates = Vec::with_capacity(wanted);
+    while candidates.len() < wanted {
+        let range = &ranges[rng.gen_range(0..ranges.len())];
+        let endpoint = Endpoint {
+            host: range.nth(rng.gen_range(0..range.len())).to_string(),
+            port: options.ports[rng.gen_range(0..options.ports.len())],
+        };
+        if seen.insert(endpoint.clone()) {
+            candidates.push(endpoint);
+        }
+    }
+    candidates
+}
@@ -0,0 +1,139 @@
+use crate::config::{Peer, WireGuardConfig};
+use crate::endpoint::Endpoint;
+use crate::naming::NodeNaming;
+use crate::node::{plan_nodes, NodeOptions};
+use serde::{Deserialize, Serialize};
+
+/* url-test代理组默认使用的测速地址和间隔(秒) */
+pub const URL_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+pub const URL_TEST_INTERVAL: u32 = 300;
+
+/* Clash.Meta(mihomo)的wireguard代理 */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ClashWireGuardProxy {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub server: String,
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<String>,
+    pub private_key: String,
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_shared_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved: Option<[u8; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_keepalive: Option<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_ips: Vec<String>,
+    pub mtu: u16,
+    pub udp: bool,
+}
+
+/* proxy-groups中的代理组 */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClashProxyGroup {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub proxies: Vec<String>,
+    pub url: String,
+    pub interval: u32,
+}
+
+/* 导出的YAML文件：proxies列表，需要时再加上proxy-groups */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ClashConfig {
+    pub proxies: Vec<ClashWireGuardProxy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proxy_groups: Vec<ClashProxyGroup>,
+}
+
+/* 从Address中分别取出第一个IPv4地址和第一个IPv6地址，去掉后面的"/32"、"/128" */
+fn split_addresses(addresses: &[String]) -> (Option<String>, Option<String>) {
+    let strip = |address: &String| address.split('/').next().unwrap_or("").to_string();
+    let ip = addresses.iter().find(|a| !a.contains(':')).map(strip);
+    let ipv6 = addresses.iter().find(|a| a.contains(':')).map(strip);
+    (ip, ipv6)
+}
+
+impl ClashWireGuardProxy {
+    /* 以WireGuard配置文件的参数为基准，使用指定的[Peer]和Endpoint生成代理 */
+    pub fn new(
+        config: &WireGuardConfig,
+        peer: &Peer,
+        endpoint: &Endpoint,
+        name: &str,
+        options: &NodeOptions,
+    ) -> Self {
+        let (ip, ipv6) = split_addresses(&config.interface.addresses);
+        ClashWireGuardProxy {
+            name: name.to_string(),
+            kind: "wireguard".to_string(),
+            server: endpoint.host.clone(),
+            port: endpoint.port,
+            ip,
+            ipv6,
+            private_key: config.interface.private_key.clone(),
+            public_key: peer.public_key.clone(),
+            pre_shared_key: peer.preshared_key.clone(),
+            reserved: options
+                .reserved_for(config, peer)
+                .map(|reserved| reserved.0),
+            persistent_keepalive: peer.persistent_keepalive,
+            allowed_ips: peer.allowed_ips.clone(),
+            mtu: options.mtu_for(config),
+            udp: true,
+        }
+    }
+}
+
+/* 批量将ip.txt中的Endpoint生成Clash.Meta的proxies列表；group不为None时再生成一个包含所有节点的url-test代理组；每个节点调用一次progress(节点名称, 生成的内容) */
+pub fn generate_clash_config<F>(
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    cross: bool,
+    group: Option<&str>,
+    mut progress: F,
+) -> Option<String>
+where
+    F: FnMut(&str, &str),
+{
+    let specs = plan_nodes(config, endpoints, cross, naming, options);
+    if specs.is_empty() {
+        return None;
+    }
+    let proxies: Vec<ClashWireGuardProxy> = specs
+        .iter()
+        .map(|spec| {
+            ClashWireGuardProxy::new(config, spec.peer, &spec.endpoint, &spec.name, options)
+        })
+        .collect();
+    let proxy_groups = match group {
+        Some(group_name) => vec![ClashProxyGroup {
+            name: group_name.to_string(),
+            kind: "url-test".to_string(),
+            proxies: proxies.iter().map(|proxy| proxy.name.clone()).collect(),
+            url: URL_TEST_URL.to_string(),
+            interval: URL_TEST_INTERVAL,
+        }],
+        None => Vec::new(),
+    };
+    for spec in &specs {
+        progress(&spec.name, "Clash代理");
+    }
+    let clash_config = ClashConfig {
+        proxies,
+        proxy_groups,
+    };
+    let yaml = serde_yaml::to_string(&clash_config).expect("无法序列化Clash配置");
+    Some(yaml.trim_end().to_string())
+}
@@ -0,0 +1,472 @@
+use crate::endpoint::Endpoint;
+use crate::error::Error;
+use crate::reserved::Reserved;
+use crate::wireguard_uri::{is_wireguard_uri, parse_wireguard_uri_list};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/* warp-go的配置文件没有Address参数，WARP分配的IPv4地址固定为这个值 */
+pub const WARP_DEFAULT_ADDRESS: &str = "172.16.0.2/32";
+
+/* [Interface]节（warp-go的配置文件中叫[Account]） */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Interface {
+    pub private_key: String,
+    pub addresses: Vec<String>,
+    pub dns: Vec<String>,
+    pub mtu: Option<u16>,
+    pub listen_port: Option<u16>,
+    // 写在[Interface]节中的Reserved/client_id，对没有单独设置reserved的[Peer]都生效
+    pub reserved: Option<Reserved>,
+    // 不认识的参数原样保留，按出现的顺序存放(参数名, 值)
+    pub extra: Vec<(String, String)>,
+}
+
+/* [Peer]节，一个配置文件可以有多个 */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Peer {
+    pub public_key: String,
+    pub preshared_key: Option<String>,
+    pub allowed_ips: Vec<String>,
+    pub endpoint: Option<Endpoint>,
+    pub persistent_keepalive: Option<u16>,
+    pub reserved: Option<Reserved>,
+    pub extra: Vec<(String, String)>,
+}
+
+/* 其他不认识的节，比如warp-go的[Script]，原样保留 */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub entries: Vec<(String, String)>,
+}
+
+/* 解析后的WireGuard配置文件 */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WireGuardConfig {
+    pub interface: Interface,
+    pub peers: Vec<Peer>,
+    pub sections: Vec<Section>,
+}
+
+/* 解析过程中正在填充的节 */
+enum Current {
+    None,
+    Interface,
+    Peer(usize),
+    Other(usize),
+}
+
+impl WireGuardConfig {
+    /* 读取wireguard配置文件并解析；文件内容是wireguard://链接时，按链接解析 */
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        let contents = fs::read_to_string(file)?;
+        Self::from_contents(&contents)
+    }
+
+    /* 文件内容是wireguard://链接时按链接解析，否则按配置文件解析 */
+    pub fn from_contents(contents: &str) -> Result<Self, Error> {
+        if is_wireguard_uri(contents) {
+            return parse_wireguard_uri_list(contents);
+        }
+        Self::parse(contents)
+    }
+
+    /* 解析wireguard配置文件的内容，支持wgcf、warp-go、WARP官方客户端导出的配置文件 */
+    pub fn parse(contents: &str) -> Result<Self, Error> {
+        let mut config = WireGuardConfig::default();
+        let mut current = Current::None;
+        let mut interface_line = None;
+        let mut peer_lines: Vec<usize> = Vec::new();
+
+        for (idx, raw_line) in contents.lines().enumerate() {
+            let line_no = idx + 1;
+            // 跟wg-quick一样，"#"后面的内容都是注释；";"开头的整行也是注释
+            let line = match raw_line.find('#') {
+                Some(pos) => &raw_line[..pos],
+                None => raw_line,
+            }
+            .trim()
+            .trim_start_matches('\u{feff}');
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                let name = line
+                    .strip_prefix('[')
+                    .and_then(|s| s.strip_suffix(']'))
+                    .map(str::trim)
+                    .ok_or_else(|| {
+                        Error::parse(line_no, format!("节名称“{}”缺少右中括号", line))
+                    })?;
+                current = match name.to_ascii_lowercase().as_str() {
+                    "interface" | "account" => {
+                        if interface_line.is_some() {
+                            return Err(Error::parse(line_no, "重复的[Interface]节"));
+                        }
+                        interface_line = Some(line_no);
+                        Current::Interface
+                    }
+                    "peer" => {
+                        config.peers.push(Peer::default());
+                        peer_lines.push(line_no);
+                        Current::Peer(config.peers.len() - 1)
+                    }
+                    _ => {
+                        config.sections.push(Section {
+                            name: name.to_string(),
+                            entries: Vec::new(),
+                        });
+                        Current::Other(config.sections.len() - 1)
+                    }
+                };
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| {
+                    Error::parse(line_no, format!("“{}”不是“参数名 = 值”的格式", line))
+                })?;
+            if key.is_empty() {
+                return Err(Error::parse(line_no, "参数名为空"));
+            }
+            match current {
+                Current::None => {
+                    return Err(Error::parse(line_no, format!("参数{}不在任何节中", key)));
+                }
+                Current::Interface => {
+                    set_interface_value(&mut config.interface, key, value, line_no)?
+                }
+                Current::Peer(i) => set_peer_value(&mut config.peers[i], key, value, line_no)?,
+                Current::Other(i) => config.sections[i]
+                    .entries
+                    .push((key.to_string(), value.to_string())),
+            }
+        }
+
+        let interface_line = interface_line.ok_or(Error::MissingKey("[Interface]节"))?;
+        if config.interface.private_key.is_empty() {
+            return Err(Error::parse(
+                interface_line,
+                "[Interface]节缺少PrivateKey参数",
+            ));
+        }
+        if config.interface.addresses.is_empty() {
+            config
+                .interface
+                .addresses
+                .push(WARP_DEFAULT_ADDRESS.to_string());
+        }
+        if config.peers.is_empty() {
+            return Err(Error::MissingKey("[Peer]节"));
+        }
+        for (peer, line_no) in config.peers.iter().zip(peer_lines) {
+            if peer.public_key.is_empty() {
+                return Err(Error::parse(line_no, "[Peer]节缺少PublicKey参数"));
+            }
+        }
+        Ok(config)
+    }
+}
+
+/* wg-quick认识的[Interface]参数(除了单独保存的PrivateKey、Address、DNS、MTU、ListenPort) */
+const WG_QUICK_INTERFACE_KEYS: [&str; 7] = [
+    "Table",
+    "FwMark",
+    "PreUp",
+    "PostUp",
+    "PreDown",
+    "PostDown",
+    "SaveConfig",
+];
+
+/* 按wg-quick的格式输出配置文件，不认识的参数和节也原样输出，可以再被本程序读取 */
+impl fmt::Display for WireGuardConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_to(f, false)
+    }
+}
+
+/* to_wg_quick的返回值，按照只有wg-quick认识的参数的格式输出 */
+pub struct WgQuickConfig<'a>(&'a WireGuardConfig);
+
+impl fmt::Display for WgQuickConfig<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write_to(f, true)
+    }
+}
+
+impl WireGuardConfig {
+    /*
+    wg-quick和官方WireGuard客户端可以直接使用的配置文件：
+        Reserved、不认识的参数和节(比如warp-go的Device、[Script])它们不支持，写成注释
+    */
+    pub fn to_wg_quick(&self) -> WgQuickConfig<'_> {
+        WgQuickConfig(self)
+    }
+
+    /* standard为true时，wg-quick不认识的参数和节写成"# "开头的注释 */
+    fn write_to(&self, f: &mut fmt::Formatter<'_>, standard: bool) -> fmt::Result {
+        let comment = if standard { "# " } else { "" };
+        let interface = &self.interface;
+        writeln!(f, "[Interface]")?;
+        writeln!(f, "PrivateKey = {}", interface.private_key)?;
+        if !interface.addresses.is_empty() {
+            writeln!(f, "Address = {}", interface.addresses.join(", "))?;
+        }
+        if !interface.dns.is_empty() {
+            writeln!(f, "DNS = {}", interface.dns.join(", "))?;
+        }
+        if let Some(mtu) = interface.mtu {
+            writeln!(f, "MTU = {}", mtu)?;
+        }
+        if let Some(port) = interface.listen_port {
+            writeln!(f, "ListenPort = {}", port)?;
+        }
+        if let Some(reserved) = interface.reserved {
+            writeln!(f, "{}Reserved = {}", comment, reserved)?;
+        }
+        for (key, value) in &interface.extra {
+            let known = WG_QUICK_INTERFACE_KEYS
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(key));
+            let comment = if known { "" } else { comment };
+            writeln!(f, "{}{} = {}", comment, key, value)?;
+        }
+
+        for peer in &self.peers {
+            writeln!(f, "\n[Peer]")?;
+            writeln!(f, "PublicKey = {}", peer.public_key)?;
+            if let Some(key) = &peer.preshared_key {
+                writeln!(f, "PresharedKey = {}", key)?;
+            }
+            if !peer.allowed_ips.is_empty() {
+                writeln!(f, "AllowedIPs = {}", peer.allowed_ips.join(", "))?;
+            }
+            if let Some(endpoint) = &peer.endpoint {
+                writeln!(f, "Endpoint = {}", endpoint)?;
+            }
+            if let Some(interval) = peer.persistent_keepalive {
+                writeln!(f, "PersistentKeepalive = {}", interval)?;
+            }
+            if let Some(reserved) = peer.reserved {
+                writeln!(f, "{}Reserved = {}", comment, reserved)?;
+            }
+            for (key, value) in &peer.extra {
+                writeln!(f, "{}{} = {}", comment, key, value)?;
+            }
+        }
+
+        for section in &self.sections {
+            writeln!(f, "\n{}[{}]", comment, section.name)?;
+            for (key, value) in &section.entries {
+                writeln!(f, "{}{} = {}", comment, key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/* 逗号分隔的列表，同一个参数出现多行时追加 */
+fn push_list(list: &mut Vec<String>, value: &str) {
+    list.extend(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string),
+    );
+}
+
+fn parse_number(key: &str, value: &str, line_no: usize) -> Result<u16, Error> {
+    value
+        .parse::<u16>()
+        .map_err(|_| Error::parse(line_no, format!("{}的值“{}”不是合法的数字", key, value)))
+}
+
+/* Reserved = 1,2,3 或者 client_id = base64编码 */
+fn parse_reserved(value: &str, line_no: usize) -> Result<Reserved, Error> {
+    value
+        .parse::<Reserved>()
+        .map_err(|message| Error::parse(line_no, message))
+}
+
+fn set_interface_value(
+    interface: &mut Interface,
+    key: &str,
+    value: &str,
+    line_no: usize,
+) -> Result<(), Error> {
+    match key.to_ascii_lowercase().as_str() {
+        "privatekey" => interface.private_key = value.to_string(),
+        "address" => push_list(&mut interface.addresses, value),
+        "dns" => push_list(&mut interface.dns, value),
+        "mtu" => interface.mtu = Some(parse_number(key, value, line_no)?),
+        "listenport" => interface.listen_port = Some(parse_number(key, value, line_no)?),
+        "reserved" | "clientid" | "client_id" => {
+            interface.reserved = Some(parse_reserved(value, line_no)?)
+        }
+        _ => interface.extra.push((key.to_string(), value.to_string())),
+    }
+    Ok(())
+}
+
+fn set_peer_value(peer: &mut Peer, key: &str, value: &str, line_no: usize) -> Result<(), Error> {
+    match key.to_ascii_lowercase().as_str() {
+        "publickey" => peer.public_key = value.to_string(),
+        "presharedkey" => peer.preshared_key = Some(value.to_string()),
+        "allowedips" => push_list(&mut peer.allowed_ips, value),
+        "endpoint" => {
+            let endpoint = Endpoint::parse(value)
+                .ok_or_else(|| Error::parse(line_no, format!("Endpoint的值“{}”不合法", value)))?;
+            peer.endpoint = Some(endpoint);
+        }
+        // warp-go的配置文件中叫KeepAlive
+        "persistentkeepalive" | "keepalive" => {
+            peer.persistent_keepalive = if value.eq_ignore_ascii_case("off") {
+                None
+            } else {
+                Some(parse_number(key, value, line_no)?)
+            }
+        }
+        "reserved" | "clientid" | "client_id" => {
+            peer.reserved = Some(parse_reserved(value, line_no)?)
+        }
+        _ => peer.extra.push((key.to_string(), value.to_string())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WGCF_PROFILE: &str = "\
+[Interface]
+PrivateKey = YFYOAdbw1bKTHlNNi+aEjBM3BO7unuFC5rOkMRAz9XY=
+Address = 172.16.0.2/32
+Address = 2606:4700:110:8a36:df92:102a:9602:fa18/128
+DNS = 1.1.1.1
+MTU = 1280
+
+[Peer]
+PublicKey = bmXOC+F1FxEMF9dyiK2H5/1SUtzH0JuVo51h2wPfgyo=
+AllowedIPs = 0.0.0.0/0, ::/0
+Endpoint = engage.cloudflareclient.com:2408
+";
+
+    /* 解析失败时返回出错的行号 */
+    fn error_line(contents: &str) -> usize {
+        match WireGuardConfig::parse(contents) {
+            Err(Error::Parse { line, .. }) => line,
+            other => panic!("应该在某一行解析失败：{:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_wgcf_profile() {
+        let config = WireGuardConfig::parse(WGCF_PROFILE).unwrap();
+        assert_eq!(config.interface.addresses.len(), 2);
+        assert_eq!(config.interface.dns, ["1.1.1.1"]);
+        assert_eq!(config.interface.mtu, Some(1280));
+        assert_eq!(config.peers.len(), 1);
+        assert_eq!(config.peers[0].allowed_ips, ["0.0.0.0/0", "::/0"]);
+        let endpoint = config.peers[0].endpoint.as_ref().unwrap();
+        assert_eq!(endpoint.host, "engage.cloudflareclient.com");
+        assert_eq!(endpoint.port, 2408);
+    }
+
+    #[test]
+    fn parses_warp_go_account() {
+        let contents = "\
+[Account]
+Device = 6ef7a8f2-4c1a-4b0a-9d2c-2a1c3c3f0b11
+PrivateKey = YFYOAdbw1bKTHlNNi+aEjBM3BO7unuFC5rOkMRAz9XY=
+Token = abc
+Type = free
+
+[Peer]
+PublicKey = bmXOC+F1FxEMF9dyiK2H5/1SUtzH0JuVo51h2wPfgyo=
+Endpoint = 162.159.192.1:2408
+KeepAlive = 30
+Reserved = 1,2,3
+
+[Script]
+PostUp = echo up
+";
+        let config = WireGuardConfig::parse(contents).unwrap();
+        // 没有Address参数时使用WARP分配的固定地址
+        assert_eq!(config.interface.addresses, [WARP_DEFAULT_ADDRESS]);
+        assert_eq!(config.interface.extra.len(), 3);
+        assert_eq!(config.peers[0].persistent_keepalive, Some(30));
+        assert_eq!(config.peers[0].reserved, Some(Reserved([1, 2, 3])));
+        assert_eq!(config.sections[0].name, "Script");
+
+        // wg-quick格式中warp-go特有的参数、Reserved和[Script]节都写成注释
+        let wg_quick = config.to_wg_quick().to_string();
+        assert!(wg_quick.contains("# Device = "));
+        assert!(wg_quick.contains("# Reserved = 1,2,3"));
+        assert!(wg_quick.contains("# [Script]"));
+        assert!(WireGuardConfig::parse(&wg_quick).is_ok());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let config = WireGuardConfig::parse(WGCF_PROFILE).unwrap();
+        assert_eq!(WireGuardConfig::parse(&config.to_string()).unwrap(), config);
+    }
+
+    #[test]
+    fn comments_and_bom_are_ignored() {
+        let contents = format!("\u{feff}# wgcf\n; comment\n{}", WGCF_PROFILE)
+            .replace("MTU = 1280", "MTU = 1280 # 减小MTU");
+        let config = WireGuardConfig::parse(&contents).unwrap();
+        assert_eq!(config.interface.mtu, Some(1280));
+    }
+
+    #[test]
+    fn section_without_closing_bracket_reports_its_line() {
+        let contents = WGCF_PROFILE.replace("[Peer]", "[Peer");
+        assert_eq!(error_line(&contents), 8);
+    }
+
+    #[test]
+    fn key_outside_any_section_reports_its_line() {
+        let contents = format!("\nMTU = 1280\n{}", WGCF_PROFILE);
+        assert_eq!(error_line(&contents), 2);
+    }
+
+    #[test]
+    fn peer_without_public_key_reports_the_section_line() {
+        let contents = WGCF_PROFILE.replace(
+            "PublicKey = bmXOC+F1FxEMF9dyiK2H5/1SUtzH0JuVo51h2wPfgyo=\n",
+            "",
+        );
+        assert_eq!(error_line(&contents), 8);
+    }
+
+    #[test]
+    fn invalid_values_report_their_line() {
+        assert_eq!(error_line(&WGCF_PROFILE.replace("1280", "abc")), 6);
+        assert_eq!(error_line(&WGCF_PROFILE.replace(":2408", ":99999")), 11);
+        assert_eq!(error_line(&WGCF_PROFILE.replace("DNS = ", "DNS ")), 5);
+    }
+
+    #[test]
+    fn missing_sections_are_reported() {
+        let no_peer = &WGCF_PROFILE[..WGCF_PROFILE.find("[Peer]").unwrap()];
+        assert!(matches!(
+            WireGuardConfig::parse(no_peer),
+            Err(Error::MissingKey("[Peer]节"))
+        ));
+        let no_interface = &WGCF_PROFILE[WGCF_PROFILE.find("[Peer]").unwrap()..];
+        assert!(matches!(
+            WireGuardConfig::parse(no_interface),
+            Err(Error::MissingKey("[Interface]节"))
+        ));
+    }
+}
@@ -0,0 +1,555 @@
+use crate::ip_range::IpRange;
+use crate::scan_result::{is_scan_result, parse_scan_result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+// 使用 lazy_static 宏创建静态正则表达式对象，使用 lazy_static 宏可以在第一次使用正则表达式时初始化它们，以后就不需要再次编译。
+lazy_static! {
+    static ref IPV4_REGEX: Regex = Regex::new(r#"^(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)$"#).unwrap();
+    static ref IPV6_REGEX: Regex = Regex::new(r#"^(?:(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}|(?:[0-9a-fA-F]{1,4}:){1,7}:|(?:[0-9a-fA-F]{1,4}:){1,6}:[0-9a-fA-F]{1,4}|(?:[0-9a-fA-F]{1,4}:){1,5}(?::[0-9a-fA-F]{1,4}){1,2}|(?:[0-9a-fA-F]{1,4}:){1,4}(?::[0-9a-fA-F]{1,4}){1,3}|(?:[0-9a-fA-F]{1,4}:){1,3}(?::[0-9a-fA-F]{1,4}){1,4}|(?:[0-9a-fA-F]{1,4}:){1,2}(?::[0-9a-fA-F]{1,4}){1,5}|[0-9a-fA-F]{1,4}:(?:(?::[0-9a-fA-F]{1,4}){1,6})|:(?:(?::[0-9a-fA-F]{1,4}){1,7}|:))$"#).unwrap();
+    static ref DOMAIN_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9-]+(\.[a-zA-Z0-9-]+)+$").unwrap(); // 匹配所有域名，包括子域名，正则表达式只是粗略匹配，不保证域名的后缀都存在)
+}
+
+/* WireGuard的Endpoint端点：主机地址(IPv4、IPv6、域名)和端口 */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+/* ip.txt中的一行不能生成Endpoint的原因 */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    InvalidHost(String),
+    InvalidPort(String),
+    PortOutOfRange(String),
+    MissingPort,
+    Ambiguous,
+    InvalidRange(String),
+    Duplicate { first_line: usize },
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::InvalidHost(host) => {
+                write!(f, "主机地址“{}”不是IPv4地址、IPv6地址或域名", host)
+            }
+            RejectReason::InvalidPort(port) => write!(f, "端口“{}”不是数字", port),
+            RejectReason::PortOutOfRange(port) => write!(f, "端口{}不在1~65535之间", port),
+            RejectReason::MissingPort => write!(f, "缺少端口"),
+            RejectReason::Ambiguous => write!(
+                f,
+                "格式不明确，应该是“主机地址:端口”、“[IPv6]:端口”或“主机地址 端口”"
+            ),
+            RejectReason::InvalidRange(message) => write!(f, "{}", message),
+            RejectReason::Duplicate { first_line } => {
+                write!(f, "跟第{}行的Endpoint重复", first_line)
+            }
+        }
+    }
+}
+
+/* 检查端口：必须是1~65535之间的数字 */
+fn parse_port(port: &str) -> Result<u16, RejectReason> {
+    if port.is_empty() {
+        return Err(RejectReason::MissingPort);
+    }
+    if !port.chars().all(|c| c.is_ascii_digit()) {
+        return Err(RejectReason::InvalidPort(port.to_string()));
+    }
+    match port.parse::<u32>() {
+        Ok(number) if (1..=65535).contains(&number) => Ok(number as u16),
+        _ => Err(RejectReason::PortOutOfRange(port.to_string())),
+    }
+}
+
+/*
+把一段文字拆分成主机地址和端口(端口可能为空)："主机地址:端口"、"[IPv6]:端口"、"[IPv6]"、"主机地址"；
+IPv6的CIDR网段可以把前缀长度写在中括号外面("[IPv6]/120:端口")
+*/
+fn split_host_port(text: &str) -> Result<(String, &str), RejectReason> {
+    if let Some(rest) = text.strip_prefix('[') {
+        let end_idx = rest.find(']').ok_or(RejectReason::Ambiguous)?;
+        let mut host = rest[..end_idx].to_string();
+        let mut after = &rest[end_idx + 1..];
+        if let Some(prefix) = after.strip_prefix('/') {
+            let prefix_end = prefix.find(':').unwrap_or(prefix.len());
+            host = format!("{}/{}", host, &prefix[..prefix_end]);
+            after = &prefix[prefix_end..];
+        }
+        let port = match after.strip_prefix(':') {
+            Some(port) => port,
+            None if after.is_empty() => "",
+            None => return Err(RejectReason::Ambiguous),
+        };
+        return Ok((host, port));
+    }
+    match text.matches(':').count() {
+        0 => Ok((text.to_string(), "")),
+        1 => {
+            let (host, port) = text.split_once(':').unwrap_or((text, ""));
+            Ok((host.to_string(), port))
+        }
+        // 没有中括号的IPv6地址、网段或范围，只能当作主机地址(只有一列时split_columns已经跳过了)
+        _ if IPV6_REGEX.is_match(text) || IpRange::parse(text).is_some() => {
+            Ok((text.to_string(), ""))
+        }
+        _ => Err(RejectReason::Ambiguous),
+    }
+}
+
+/*
+把一行的几列拆分成主机地址(不检查是否合法)和端口(可能为空)："主机地址:端口"或者"主机地址 端口"；
+只有一列、没有中括号的IPv6地址或范围(比如2606:4700::1:2408)无法区分最后一段是地址还是端口，作为格式不明确跳过
+*/
+fn split_columns<'a>(columns: &[&'a str]) -> Result<(String, &'a str), RejectReason> {
+    match columns {
+        [single]
+            if !single.starts_with('[')
+                && !single.contains('/')
+                && single.matches(':').count() > 1 =>
+        {
+            Err(RejectReason::Ambiguous)
+        }
+        [single] => split_host_port(single),
+        [host_part, port] => match split_host_port(host_part)? {
+            (host, "") => Ok((host, *port)),
+            // "主机地址:端口 端口"这样写了两个端口
+            _ => Err(RejectReason::Ambiguous),
+        },
+        [] => Err(RejectReason::MissingPort),
+        _ => Err(RejectReason::Ambiguous),
+    }
+}
+
+/* 检查端口，空字符串表示省略了端口 */
+fn parse_optional_port(port: &str) -> Result<Option<u16>, RejectReason> {
+    match port {
+        "" => Ok(None),
+        port => Ok(Some(parse_port(port)?)),
+    }
+}
+
+impl Endpoint {
+    /* 解析"主机地址:端口"、"[IPv6]:端口"、"主机地址 端口"这几种写法，不合法的返回None */
+    pub fn parse(line: &str) -> Option<Self> {
+        Self::parse_line(line).ok()
+    }
+
+    /* 跟parse一样，不合法时返回原因 */
+    pub fn parse_line(line: &str) -> Result<Self, RejectReason> {
+        let (host, port) = Self::parse_target(line)?;
+        let port = port.ok_or(RejectReason::MissingPort)?;
+        Ok(Endpoint { host, port })
+    }
+
+    /* 解析主机地址和端口，端口可以省略(只有主机地址时返回None) */
+    pub fn parse_target(line: &str) -> Result<(String, Option<u16>), RejectReason> {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let (host, port) = split_columns(&columns)?;
+        // 不是IPv4地址、IPv6地址、域名主机的都跳过
+        if !is_valid_host(&host) {
+            return Err(RejectReason::InvalidHost(host));
+        }
+        Ok((host, parse_optional_port(port)?))
+    }
+
+    /* 遇到主机是IPv6地址时，添加中括号 */
+    pub fn host_name(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host_name(), self.port)
+    }
+}
+
+/* 命令行参数中的Endpoint端点(比如--endpoint 162.159.192.1:2408) */
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Endpoint::parse(s).ok_or_else(|| {
+            format!(
+                "Endpoint端点“{}”不合法，应该是主机地址:端口号(比如162.159.192.1:2408)",
+                s.trim()
+            )
+        })
+    }
+}
+
+/* ip.txt中被跳过的一行：行号、原来的内容和原因 */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedLine {
+    pub line: usize,
+    pub text: String,
+    pub reason: RejectReason,
+}
+
+impl fmt::Display for RejectedLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第{}行“{}”：{}", self.line, self.text, self.reason)
+    }
+}
+
+/* 扫描器(CloudflareST、warp-yxip)测出的Endpoint延迟(毫秒)和丢包率(百分比)，没有测量值的为None */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EndpointMetrics {
+    pub latency: Option<f64>,
+    pub loss: Option<f64>,
+}
+
+/* 解析ip.txt的结果：可以使用的Endpoint、被跳过的行，以及Endpoint的测量值 */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointList {
+    pub endpoints: Vec<Endpoint>,
+    pub rejected: Vec<RejectedLine>,
+    pub metrics: HashMap<Endpoint, EndpointMetrics>,
+}
+
+impl EndpointList {
+    /* 每种原因跳过了几行，按照第一次出现的顺序排列 */
+    pub fn reject_counts(&self) -> Vec<(&'static str, usize)> {
+        let mut counts: Vec<(&'static str, usize)> = Vec::new();
+        for rejected in &self.rejected {
+            let label = match rejected.reason {
+                RejectReason::InvalidHost(_) => "主机地址不合法",
+                RejectReason::InvalidPort(_) | RejectReason::PortOutOfRange(_) => "端口不合法",
+                RejectReason::MissingPort => "缺少端口",
+                RejectReason::Ambiguous => "格式不明确",
+                RejectReason::InvalidRange(_) => "网段或范围不合法",
+                RejectReason::Duplicate { .. } => "重复",
+            };
+            match counts.iter_mut().find(|(existing, _)| *existing == label) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((label, 1)),
+            }
+        }
+        counts
+    }
+
+    /*
+    去掉延迟或丢包率超过上限的Endpoint，没有测量值的保留，返回去掉了几个；
+    丢包率为100%的(一次响应都没有收到，所以没有延迟)算作延迟超过上限
+    */
+    pub fn filter_by_metrics(&mut self, max_latency: Option<f64>, max_loss: Option<f64>) -> usize {
+        let before = self.endpoints.len();
+        let metrics = &self.metrics;
+        self.endpoints.retain(|endpoint| {
+            let measured = metrics.get(endpoint).copied().unwrap_or_default();
+            let exceeds = |value: Option<f64>, limit: Option<f64>| match (value, limit) {
+                (Some(value), Some(limit)) => value > limit,
+                _ => false,
+            };
+            let unreachable =
+                measured.latency.is_none() && measured.loss.is_some_and(|loss| loss >= 100.0);
+            let too_slow = max_latency.is_some() && unreachable;
+            !too_slow
+                && !exceeds(measured.latency, max_latency)
+                && !exceeds(measured.loss, max_loss)
+        });
+        let kept: HashSet<&Endpoint> = self.endpoints.iter().collect();
+        self.metrics.retain(|endpoint, _| kept.contains(endpoint));
+        before - self.endpoints.len()
+    }
+}
+
+/*
+把ip.txt中的一行展开成Endpoint：
+    1、CIDR网段和IP范围展开成其中的每个地址，同一行写了"sample=数量"时随机抽取这么多个地址；
+    2、没有端口的主机地址、网段、范围跟bare_ports中的每个端口组合。
+*/
+pub(crate) fn expand_line(line: &str, bare_ports: &[u16]) -> Result<Vec<Endpoint>, RejectReason> {
+    let mut sample = None;
+    let mut columns = Vec::new();
+    for column in line.split_whitespace() {
+        match column.strip_prefix("sample=") {
+            Some(value) => match value.parse::<usize>() {
+                Ok(count) if count > 0 => sample = Some(count),
+                _ => {
+                    return Err(RejectReason::InvalidRange(format!(
+                        "sample的值“{}”不是正整数",
+                        value
+                    )))
+                }
+            },
+            None => columns.push(column),
+        }
+    }
+    let (host, port) = split_columns(&columns)?;
+    let hosts: Vec<String> = match IpRange::parse(&host) {
+        Some(range) => range
+            .and_then(|range| range.addresses(sample))
+            .map_err(RejectReason::InvalidRange)?
+            .into_iter()
+            .map(|address| address.to_string())
+            .collect(),
+        None if is_valid_host(&host) => vec![host],
+        None => return Err(RejectReason::InvalidHost(host)),
+    };
+    let ports = match parse_optional_port(port)? {
+        Some(port) => vec![port],
+        None if bare_ports.is_empty() => return Err(RejectReason::MissingPort),
+        None => bare_ports.to_vec(),
+    };
+    Ok(hosts
+        .iter()
+        .flat_map(|host| {
+            ports.iter().map(move |port| Endpoint {
+                host: host.clone(),
+                port: *port,
+            })
+        })
+        .collect())
+}
+
+/*
+把每一行展开的结果合并成EndpointList：(行号, 原来的内容, 展开的结果, 测量值)；
+展开后只有一部分重复时，去掉重复的，其他的照常使用；不合法的行、全部重复的行记录行号和原因
+*/
+pub(crate) fn collect_lines<I>(lines: I) -> EndpointList
+where
+    I: IntoIterator<
+        Item = (
+            usize,
+            String,
+            Result<Vec<Endpoint>, RejectReason>,
+            EndpointMetrics,
+        ),
+    >,
+{
+    let mut list = EndpointList::default();
+    let mut first_lines: HashMap<Endpoint, usize> = HashMap::new();
+    for (line, text, result, metrics) in lines {
+        let result = result.and_then(|endpoints| {
+            let first_line = endpoints
+                .iter()
+                .filter_map(|endpoint| first_lines.get(endpoint).copied())
+                .min();
+            let fresh: Vec<Endpoint> = endpoints
+                .into_iter()
+                .filter(|endpoint| !first_lines.contains_key(endpoint))
+                .collect();
+            match first_line {
+                Some(first_line) if fresh.is_empty() => Err(RejectReason::Duplicate { first_line }),
+                _ => Ok(fresh),
+            }
+        });
+        match result {
+            Ok(endpoints) => {
+                for endpoint in endpoints {
+                    if first_lines.insert(endpoint.clone(), line).is_none() {
+                        if metrics != EndpointMetrics::default() {
+                            list.metrics.insert(endpoint.clone(), metrics);
+                        }
+                        list.endpoints.push(endpoint);
+                    }
+                }
+            }
+            Err(reason) => list.rejected.push(RejectedLine { line, text, reason }),
+        }
+    }
+    list
+}
+
+/*
+解析ip.txt的内容：空行和"#"开头的注释跳过，行尾"#"后面的内容是注释；
+没有端口的主机地址跟bare_ports中的每个端口组合；
+内容是CloudflareST、warp-yxip等扫描器输出的result.csv时，读取其中的Endpoint列和延迟、丢包率
+*/
+pub fn parse_endpoint_lines(contents: &str, bare_ports: &[u16]) -> EndpointList {
+    if is_scan_result(contents) {
+        return parse_scan_result(contents, bare_ports);
+    }
+    collect_lines(contents.lines().enumerate().filter_map(|(idx, raw_line)| {
+        let line = raw_line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            return None;
+        }
+        Some((
+            idx + 1,
+            line.to_string(),
+            expand_line(line, bare_ports),
+            EndpointMetrics::default(),
+        ))
+    }))
+}
+
+/* 检查主机地址是否为IPv4地址、IPv6地址或域名 */
+pub fn is_valid_host(host: &str) -> bool {
+    IPV4_REGEX.is_match(host) || IPV6_REGEX.is_match(host) || DOMAIN_REGEX.is_match(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(host: &str, port: u16) -> Endpoint {
+        Endpoint {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    #[test]
+    fn parses_host_and_port_forms() {
+        for (line, host, port) in [
+            ("162.159.192.1:2408", "162.159.192.1", 2408),
+            ("162.159.192.1 2408", "162.159.192.1", 2408),
+            ("  162.159.192.1\t2408  ", "162.159.192.1", 2408),
+            (
+                "[2606:4700:d0::a29f:c001]:864",
+                "2606:4700:d0::a29f:c001",
+                864,
+            ),
+            (
+                "[2606:4700:d0::a29f:c001] 864",
+                "2606:4700:d0::a29f:c001",
+                864,
+            ),
+            (
+                "2606:4700:d0::a29f:c001 864",
+                "2606:4700:d0::a29f:c001",
+                864,
+            ),
+            (
+                "engage.cloudflareclient.com:2408",
+                "engage.cloudflareclient.com",
+                2408,
+            ),
+        ] {
+            assert_eq!(
+                Endpoint::parse_line(line),
+                Ok(endpoint(host, port)),
+                "{}",
+                line
+            );
+        }
+        // IPv6地址输出时加上中括号，可以再解析回来
+        let v6 = endpoint("2606:4700:d0::a29f:c001", 864);
+        assert_eq!(v6.to_string(), "[2606:4700:d0::a29f:c001]:864");
+        assert_eq!(v6.to_string().parse::<Endpoint>().unwrap(), v6);
+    }
+
+    #[test]
+    fn rejects_invalid_lines_with_a_reason() {
+        assert_eq!(
+            Endpoint::parse_line("162.159.192.1"),
+            Err(RejectReason::MissingPort)
+        );
+        assert_eq!(
+            Endpoint::parse_line("162.159.192.1:abc"),
+            Err(RejectReason::InvalidPort("abc".to_string()))
+        );
+        assert_eq!(
+            Endpoint::parse_line("162.159.192.1:65536"),
+            Err(RejectReason::PortOutOfRange("65536".to_string()))
+        );
+        assert_eq!(
+            Endpoint::parse_line("162.159.192.1:0"),
+            Err(RejectReason::PortOutOfRange("0".to_string()))
+        );
+        assert_eq!(
+            Endpoint::parse_line("not_a_host:2408"),
+            Err(RejectReason::InvalidHost("not_a_host".to_string()))
+        );
+        // 写了两个端口
+        assert_eq!(
+            Endpoint::parse_line("162.159.192.1:2408 500"),
+            Err(RejectReason::Ambiguous)
+        );
+        assert_eq!(
+            Endpoint::parse_line("[2606:4700:d0::a29f:c001:864"),
+            Err(RejectReason::Ambiguous)
+        );
+    }
+
+    #[test]
+    fn bare_ipv6_in_one_column_is_ambiguous() {
+        // 最后一段可能是地址的一部分，也可能是端口
+        assert_eq!(
+            Endpoint::parse_line("2606:4700:d0::a29f:c001:864"),
+            Err(RejectReason::Ambiguous)
+        );
+        assert_eq!(
+            Endpoint::parse_target("2606:4700:d0::a29f:c001"),
+            Err(RejectReason::Ambiguous)
+        );
+        // 有了中括号就能区分
+        assert_eq!(
+            Endpoint::parse_target("[2606:4700:d0::a29f:c001]"),
+            Ok(("2606:4700:d0::a29f:c001".to_string(), None))
+        );
+        let list = parse_endpoint_lines("2606:4700:d0::a29f:c001\n", &[2408]);
+        assert!(list.endpoints.is_empty());
+        assert_eq!(list.rejected[0].reason, RejectReason::Ambiguous);
+    }
+
+    #[test]
+    fn bare_hosts_use_every_port() {
+        let list = parse_endpoint_lines("162.159.192.1\n", &[2408, 500]);
+        assert_eq!(
+            list.endpoints,
+            [
+                endpoint("162.159.192.1", 2408),
+                endpoint("162.159.192.1", 500)
+            ]
+        );
+        let list = parse_endpoint_lines("162.159.192.1\n", &[]);
+        assert_eq!(list.rejected[0].reason, RejectReason::MissingPort);
+    }
+
+    #[test]
+    fn comments_blank_lines_and_line_numbers() {
+        let contents = "# 优选IP\n\n162.159.192.1:2408 # 最快\nbad line here\n162.159.192.2:2408\n";
+        let list = parse_endpoint_lines(contents, &[]);
+        assert_eq!(
+            list.endpoints,
+            [
+                endpoint("162.159.192.1", 2408),
+                endpoint("162.159.192.2", 2408)
+            ]
+        );
+        assert_eq!(list.rejected.len(), 1);
+        assert_eq!(list.rejected[0].line, 4);
+        assert_eq!(list.rejected[0].text, "bad line here");
+        assert_eq!(list.reject_counts(), [("格式不明确", 1)]);
+    }
+
+    #[test]
+    fn duplicate_lines_point_to_the_first_occurrence() {
+        let contents = "162.159.192.1:2408\n162.159.192.2:2408\n162.159.192.1 2408\n";
+        let list = parse_endpoint_lines(contents, &[]);
+        assert_eq!(list.endpoints.len(), 2);
+        assert_eq!(list.rejected.len(), 1);
+        assert_eq!(list.rejected[0].line, 3);
+        assert_eq!(
+            list.rejected[0].reason,
+            RejectReason::Duplicate { first_line: 1 }
+        );
+    }
+
+    #[test]
+    fn partly_duplicate_lines_keep_the_new_endpoints() {
+        let contents = "162.159.192.1:2408\n162.159.192.1\n";
+        let list = parse_endpoint_lines(contents, &[2408, 500]);
+        assert_eq!(
+            list.endpoints,
+            [
+                endpoint("162.159.192.1", 2408),
+                endpoint("162.159.192.1", 500)
+            ]
+        );
+        assert!(list.rejected.is_empty());
+    }
+}
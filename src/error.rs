@@ -0,0 +1,57 @@
+use std::fmt;
+use std::io;
+
+/* 库中所有可能出现的错误 */
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    MissingKey(&'static str),
+    InvalidValue { key: &'static str, value: String },
+    Parse { line: usize, message: String },
+    InvalidLink(String),
+    Settings { file: String, message: String },
+    GeoIp { file: String, message: String },
+}
+
+impl Error {
+    /* 配置文件第line行解析失败 */
+    pub fn parse<S: Into<String>>(line: usize, message: S) -> Self {
+        Error::Parse {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "文件读写失败：{}", err),
+            Error::MissingKey(key) => write!(f, "WireGuard配置文件中缺少{}", key),
+            Error::InvalidValue { key, value } => write!(f, "{}的值“{}”不合法", key, value),
+            Error::Parse { line, message } => write!(f, "配置文件第{}行：{}", line, message),
+            Error::InvalidLink(message) => write!(f, "无法解析节点链接：{}", message),
+            Error::Settings { file, message } => {
+                write!(f, "设置文件{}的格式不正确：{}", file, message)
+            }
+            Error::GeoIp { file, message } => {
+                write!(f, "无法打开GeoIP数据库{}：{}", file, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::config::WireGuardConfig;
+use crate::nekoray::{render_nekoray_link, NekorayFormat};
+use crate::node::{NodeOptions, NodeSpec};
+use crate::wireguard_uri::render_wireguard_uri;
+use std::str::FromStr;
+
+/* 批量生成时的输出格式 */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Nekoray,
+    Nekobox,
+    SingBox,
+    Clash,
+    WireGuardUri,
+    Xray,
+    WgQuick,
+    OpenWrt,
+    Networkd,
+}
+
+impl OutputFormat {
+    /* nekoray链接使用哪种节点，其他格式返回None */
+    pub fn nekoray_format(&self) -> Option<NekorayFormat> {
+        match self {
+            OutputFormat::Nekoray => Some(NekorayFormat::Custom),
+            OutputFormat::Nekobox => Some(NekorayFormat::Native),
+            _ => None,
+        }
+    }
+
+    /* 是不是每个节点一行链接的格式(nekoray链接、wireguard://链接) */
+    pub fn is_link(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Nekoray | OutputFormat::Nekobox | OutputFormat::WireGuardUri
+        )
+    }
+
+    /* 每个节点生成一条链接的格式(nekoray链接、wireguard://链接)，其他格式返回None */
+    pub fn render_link(
+        &self,
+        config: &WireGuardConfig,
+        spec: &NodeSpec,
+        options: &NodeOptions,
+    ) -> Option<String> {
+        match self {
+            OutputFormat::Nekoray | OutputFormat::Nekobox => Some(render_nekoray_link(
+                config,
+                spec.peer,
+                &spec.endpoint,
+                &spec.name,
+                options,
+            )),
+            OutputFormat::WireGuardUri => Some(render_wireguard_uri(
+                config,
+                spec.peer,
+                &spec.endpoint,
+                &spec.name,
+                options,
+            )),
+            _ => None,
+        }
+    }
+
+    /* 没有指定输出文件时使用的文件名(输出目录的格式是目录名) */
+    pub fn default_output_file(&self) -> &'static str {
+        match self {
+            OutputFormat::Nekoray | OutputFormat::Nekobox | OutputFormat::WireGuardUri => {
+                "output.txt"
+            }
+            OutputFormat::SingBox | OutputFormat::Xray => "output.json",
+            OutputFormat::Clash => "output.yaml",
+            OutputFormat::WgQuick => "wg-quick",
+            OutputFormat::OpenWrt => "network.uci",
+            OutputFormat::Networkd => "networkd",
+        }
+    }
+
+    /* 提示信息中显示的名称 */
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputFormat::Nekoray | OutputFormat::Nekobox => "Nekoray链接",
+            OutputFormat::SingBox => "sing-box配置",
+            OutputFormat::Clash => "Clash配置",
+            OutputFormat::WireGuardUri => "wireguard链接",
+            OutputFormat::Xray => "Xray配置",
+            OutputFormat::WgQuick => "WireGuard配置文件",
+            OutputFormat::OpenWrt => "OpenWrt配置",
+            OutputFormat::Networkd => "systemd-networkd配置文件",
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(nekoray_format) = s.parse::<NekorayFormat>() {
+            return Ok(match nekoray_format {
+                NekorayFormat::Custom => OutputFormat::Nekoray,
+                NekorayFormat::Native => OutputFormat::Nekobox,
+            });
+        }
+        match s.trim().to_ascii_lowercase().as_str() {
+            "singbox" | "sing-box" => Ok(OutputFormat::SingBox),
+            "clash" | "clash-meta" | "mihomo" => Ok(OutputFormat::Clash),
+            "wireguard" | "wg" | "v2rayn" => Ok(OutputFormat::WireGuardUri),
+            "xray" | "xray-core" => Ok(OutputFormat::Xray),
+            "wg-quick" | "wgquick" | "conf" => Ok(OutputFormat::WgQuick),
+            "openwrt" | "uci" => Ok(OutputFormat::OpenWrt),
+            "networkd" | "systemd-networkd" => Ok(OutputFormat::Networkd),
+            _ => Err(format!(
+                "不支持的输出格式“{}”，可选的值：custom、native、singbox、clash、wireguard、xray、wg-quick、openwrt、networkd",
+                s.trim()
+            )),
+        }
+    }
+}
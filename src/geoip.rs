@@ -0,0 +1,144 @@
+use crate::endpoint::Endpoint;
+use crate::error::Error;
+use maxminddb::{geoip2, Reader};
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+/* 找不到国家或地区的Endpoint，按国家或地区拆分输出时使用的代码 */
+pub const UNKNOWN_COUNTRY: &str = "XX";
+
+/* 主机地址的地理信息：国家或地区代码(ISO 3166，比如HK)、自治系统编号和名称 */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoInfo {
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+}
+
+impl GeoInfo {
+    /* 国家或地区代码对应的旗帜emoji，比如HK => 🇭🇰 */
+    pub fn flag(&self) -> Option<String> {
+        self.country.as_deref().and_then(flag_emoji)
+    }
+}
+
+/* 两个字母的国家或地区代码转换成旗帜emoji(两个区域指示符号) */
+pub fn flag_emoji(code: &str) -> Option<String> {
+    if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    code.to_ascii_uppercase()
+        .chars()
+        .map(|c| char::from_u32(0x1F1E6 + (c as u32 - 'A' as u32)))
+        .collect()
+}
+
+/*
+本地的MaxMind/DB-IP数据库(.mmdb文件)，不需要联网：
+    国家数据库(GeoLite2-Country、dbip-country-lite等)提供国家或地区代码，
+    ASN数据库(GeoLite2-ASN、dbip-asn-lite等)提供自治系统编号和名称，可以同时使用多个文件。
+*/
+pub struct GeoIpDatabase {
+    readers: Vec<Reader<Vec<u8>>>,
+}
+
+impl GeoIpDatabase {
+    /* 打开所有的数据库文件，有一个打不开时返回错误 */
+    pub fn open<S: AsRef<str>>(files: &[S]) -> Result<Self, Error> {
+        let readers = files
+            .iter()
+            .map(|file| {
+                Reader::open_readfile(file.as_ref()).map_err(|err| Error::GeoIp {
+                    file: file.as_ref().to_string(),
+                    message: err.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(GeoIpDatabase { readers })
+    }
+
+    /* 查询主机地址的地理信息，域名(不做DNS解析)和数据库中找不到的地址返回None */
+    pub fn lookup(&self, host: &str) -> Option<GeoInfo> {
+        let address: IpAddr = host.parse().ok()?;
+        let mut info = GeoInfo::default();
+        for reader in &self.readers {
+            if info.country.is_none() {
+                if let Ok(record) = reader.lookup::<geoip2::Country>(address) {
+                    info.country = record
+                        .country
+                        .or(record.registered_country)
+                        .and_then(|country| country.iso_code)
+                        .map(str::to_string);
+                }
+            }
+            if info.asn.is_none() {
+                if let Ok(record) = reader.lookup::<geoip2::Asn>(address) {
+                    info.asn = record.autonomous_system_number;
+                    info.organization = record.autonomous_system_organization.map(str::to_string);
+                }
+            }
+        }
+        (info != GeoInfo::default()).then_some(info)
+    }
+
+    /* 查询所有Endpoint的地理信息 */
+    pub fn annotate(&self, endpoints: &[Endpoint]) -> HashMap<Endpoint, GeoInfo> {
+        endpoints
+            .iter()
+            .filter_map(|endpoint| {
+                self.lookup(&endpoint.host)
+                    .map(|info| (endpoint.clone(), info))
+            })
+            .collect()
+    }
+}
+
+/* 按照国家或地区、ASN过滤Endpoint：countries、asns不为空时只保留其中的，exclude_countries中的都去掉 */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoFilter {
+    pub countries: Vec<String>,
+    pub exclude_countries: Vec<String>,
+    pub asns: Vec<u32>,
+}
+
+impl GeoFilter {
+    pub fn is_empty(&self) -> bool {
+        self.countries.is_empty() && self.exclude_countries.is_empty() && self.asns.is_empty()
+    }
+
+    /* 找不到地理信息的Endpoint，指定了countries或asns时去掉，只指定了exclude_countries时保留 */
+    pub fn matches(&self, info: Option<&GeoInfo>) -> bool {
+        let country = info.and_then(|info| info.country.as_deref());
+        let asn = info.and_then(|info| info.asn);
+        let listed = |codes: &[String]| {
+            country
+                .is_some_and(|country| codes.iter().any(|code| code.eq_ignore_ascii_case(country)))
+        };
+        (self.countries.is_empty() || listed(&self.countries))
+            && !listed(&self.exclude_countries)
+            && (self.asns.is_empty() || asn.is_some_and(|asn| self.asns.contains(&asn)))
+    }
+
+    /* 去掉不符合条件的Endpoint，返回去掉了几个 */
+    pub fn apply(&self, endpoints: &mut Vec<Endpoint>, geo: &HashMap<Endpoint, GeoInfo>) -> usize {
+        let before = endpoints.len();
+        endpoints.retain(|endpoint| self.matches(geo.get(endpoint)));
+        before - endpoints.len()
+    }
+}
+
+/* 按照国家或地区把Endpoint分组，找不到的归入UNKNOWN_COUNTRY，按照代码排列 */
+pub fn group_by_country(
+    endpoints: &[Endpoint],
+    geo: &HashMap<Endpoint, GeoInfo>,
+) -> Vec<(String, Vec<Endpoint>)> {
+    let mut groups: BTreeMap<String, Vec<Endpoint>> = BTreeMap::new();
+    for endpoint in endpoints {
+        let country = geo
+            .get(endpoint)
+            .and_then(|info| info.country.clone())
+            .unwrap_or_else(|| UNKNOWN_COUNTRY.to_string());
+        groups.entry(country).or_default().push(endpoint.clone());
+    }
+    groups.into_iter().collect()
+}
@@ -0,0 +1,405 @@
+use blake2::digest::consts::U16;
+use blake2::{Blake2s256, Blake2sMac, Digest};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Mac, SimpleHmac};
+use rand::rngs::OsRng;
+use std::time::{SystemTime, UNIX_EPOCH};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/* WireGuard握手使用的Noise协议名称、标识和mac1的标签 */
+const CONSTRUCTION: &[u8] = b"Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s";
+const IDENTIFIER: &[u8] = b"WireGuard v1 zx2c4 Jason@zx2c4.com";
+const LABEL_MAC1: &[u8] = b"mac1----";
+
+/* 握手初始消息(类型1)和握手响应(类型2)的长度 */
+pub const INITIATION_LEN: usize = 148;
+pub const RESPONSE_LEN: usize = 92;
+const MESSAGE_INITIATION: u8 = 1;
+const MESSAGE_RESPONSE: u8 = 2;
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Blake2s256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = <SimpleHmac<Blake2s256> as KeyInit>::new_from_slice(key)
+        .expect("HMAC可以使用任意长度的密钥");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/* Noise协议的KDF：从chaining key和输入派生出N个32字节的值 */
+fn kdf<const N: usize>(key: &[u8; 32], input: &[u8]) -> [[u8; 32]; N] {
+    let secret = hmac(key, &[input]);
+    let mut outputs = [[0u8; 32]; N];
+    let mut previous: Vec<u8> = Vec::new();
+    for (index, output) in outputs.iter_mut().enumerate() {
+        *output = hmac(&secret, &[&previous, &[index as u8 + 1]]);
+        previous = output.to_vec();
+    }
+    outputs
+}
+
+/* mac1：以"mac1----"和接收方公钥的哈希为密钥，对消息前面的部分计算BLAKE2s-128 */
+fn mac1(receiver_public: &[u8; 32], message: &[u8]) -> [u8; 16] {
+    let key = hash(&[LABEL_MAC1, receiver_public]);
+    let mut mac =
+        <Blake2sMac<U16> as KeyInit>::new_from_slice(&key).expect("mac1的密钥长度为32字节");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/* 握手消息中的AEAD加密都只使用一次密钥，计数器固定为0 */
+fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(
+            Nonce::from_slice(&[0u8; 12]),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("ChaCha20Poly1305加密失败")
+}
+
+fn open(key: &[u8; 32], ciphertext: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(&[0u8; 12]),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .ok()
+}
+
+/* TAI64N格式的当前时间，握手初始消息中的时间戳 */
+fn tai64n() -> [u8; 12] {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut timestamp = [0u8; 12];
+    timestamp[..8].copy_from_slice(&(0x4000_0000_0000_000a_u64 + now.as_secs()).to_be_bytes());
+    timestamp[8..].copy_from_slice(&now.subsec_nanos().to_be_bytes());
+    timestamp
+}
+
+/* 协议名称和接收方公钥决定的初始chaining key和hash */
+fn initial_state(responder_public: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+    let chaining_key = hash(&[CONSTRUCTION]);
+    let hash_value = hash(&[&hash(&[&chaining_key, IDENTIFIER]), responder_public]);
+    (chaining_key, hash_value)
+}
+
+/* 消息类型和reserved字段：WARP把reserved的3个字节写在类型后面，计算mac1时这3个字节为0 */
+fn header(message_type: u8) -> [u8; 4] {
+    [message_type, 0, 0, 0]
+}
+
+/* 复制一份消息，把reserved字段清零后用来检查mac1 */
+fn without_reserved(packet: &[u8]) -> Vec<u8> {
+    let mut packet = packet.to_vec();
+    packet[1..4].fill(0);
+    packet
+}
+
+/* 从base64解码32字节的WireGuard密钥，长度不对时返回None */
+pub fn decode_key(text: &str) -> Option<[u8; 32]> {
+    base64::decode(text.trim()).ok()?.try_into().ok()
+}
+
+/* 私钥对应的公钥 */
+pub fn public_key_of(private_key: &[u8; 32]) -> [u8; 32] {
+    PublicKey::from(&StaticSecret::from(*private_key)).to_bytes()
+}
+
+/* 握手发起方：生成握手初始消息，保留中间状态用来验证对方的握手响应 */
+pub struct Initiation {
+    pub packet: Vec<u8>,
+    pub sender_index: u32,
+    chaining_key: [u8; 32],
+    hash: [u8; 32],
+    ephemeral: StaticSecret,
+    static_private: StaticSecret,
+    static_public: [u8; 32],
+    preshared_key: [u8; 32],
+}
+
+impl Initiation {
+    /* 使用配置文件的私钥和[Peer]的公钥生成握手初始消息(Noise_IKpsk2的第一条消息) */
+    pub fn new(
+        private_key: &[u8; 32],
+        peer_public: &[u8; 32],
+        preshared_key: Option<[u8; 32]>,
+        reserved: Option<[u8; 3]>,
+        sender_index: u32,
+    ) -> Self {
+        Self::with_ephemeral(
+            private_key,
+            peer_public,
+            preshared_key,
+            reserved,
+            sender_index,
+            StaticSecret::random_from_rng(OsRng),
+            tai64n(),
+        )
+    }
+
+    /* 使用指定的临时私钥和时间戳生成握手初始消息 */
+    fn with_ephemeral(
+        private_key: &[u8; 32],
+        peer_public: &[u8; 32],
+        preshared_key: Option<[u8; 32]>,
+        reserved: Option<[u8; 3]>,
+        sender_index: u32,
+        ephemeral: StaticSecret,
+        timestamp: [u8; 12],
+    ) -> Self {
+        let static_private = StaticSecret::from(*private_key);
+        let static_public = PublicKey::from(&static_private).to_bytes();
+        let peer = PublicKey::from(*peer_public);
+        let (chaining_key, hash_value) = initial_state(peer_public);
+
+        let ephemeral_public = PublicKey::from(&ephemeral).to_bytes();
+        let [chaining_key] = kdf::<1>(&chaining_key, &ephemeral_public);
+        let hash_value = hash(&[&hash_value, &ephemeral_public]);
+
+        let [chaining_key, key] =
+            kdf::<2>(&chaining_key, ephemeral.diffie_hellman(&peer).as_bytes());
+        let encrypted_static = seal(&key, &static_public, &hash_value);
+        let hash_value = hash(&[&hash_value, &encrypted_static]);
+
+        let [chaining_key, key] = kdf::<2>(
+            &chaining_key,
+            static_private.diffie_hellman(&peer).as_bytes(),
+        );
+        let encrypted_timestamp = seal(&key, &timestamp, &hash_value);
+        let hash_value = hash(&[&hash_value, &encrypted_timestamp]);
+
+        let mut packet = Vec::with_capacity(INITIATION_LEN);
+        packet.extend_from_slice(&header(MESSAGE_INITIATION));
+        packet.extend_from_slice(&sender_index.to_le_bytes());
+        packet.extend_from_slice(&ephemeral_public);
+        packet.extend_from_slice(&encrypted_static);
+        packet.extend_from_slice(&encrypted_timestamp);
+        let mac = mac1(peer_public, &packet);
+        packet.extend_from_slice(&mac);
+        // 没有cookie，mac2全部为0
+        packet.extend_from_slice(&[0u8; 16]);
+        if let Some(reserved) = reserved {
+            packet[1..4].copy_from_slice(&reserved);
+        }
+
+        Initiation {
+            packet,
+            sender_index,
+            chaining_key,
+            hash: hash_value,
+            ephemeral,
+            static_private,
+            static_public,
+            preshared_key: preshared_key.unwrap_or([0u8; 32]),
+        }
+    }
+
+    /* 验证握手响应：消息类型、接收方序号、mac1和加密的空内容都正确时返回true */
+    pub fn verify_response(&self, packet: &[u8]) -> bool {
+        if packet.len() != RESPONSE_LEN
+            || packet[0] != MESSAGE_RESPONSE
+            || packet[8..12] != self.sender_index.to_le_bytes()
+        {
+            return false;
+        }
+        let zeroed = without_reserved(packet);
+        if mac1(&self.static_public, &zeroed[..60]) != zeroed[60..76] {
+            return false;
+        }
+        let responder_ephemeral: [u8; 32] = packet[12..44].try_into().expect("长度为32字节");
+        let responder = PublicKey::from(responder_ephemeral);
+        let [chaining_key] = kdf::<1>(&self.chaining_key, &responder_ephemeral);
+        let hash_value = hash(&[&self.hash, &responder_ephemeral]);
+        let [chaining_key] = kdf::<1>(
+            &chaining_key,
+            self.ephemeral.diffie_hellman(&responder).as_bytes(),
+        );
+        let [chaining_key] = kdf::<1>(
+            &chaining_key,
+            self.static_private.diffie_hellman(&responder).as_bytes(),
+        );
+        let [_, tau, key] = kdf::<3>(&chaining_key, &self.preshared_key);
+        let hash_value = hash(&[&hash_value, &tau]);
+        open(&key, &packet[44..60], &hash_value).is_some()
+    }
+}
+
+/*
+握手响应方(比如本地测试用的WARP替身)：验证握手初始消息并生成握手响应；
+mac1不对、解密失败的消息返回None，发起方的公钥不做限制
+*/
+pub fn respond(
+    private_key: &[u8; 32],
+    preshared_key: Option<[u8; 32]>,
+    packet: &[u8],
+    sender_index: u32,
+) -> Option<Vec<u8>> {
+    if packet.len() != INITIATION_LEN || packet[0] != MESSAGE_INITIATION {
+        return None;
+    }
+    let static_private = StaticSecret::from(*private_key);
+    let static_public = PublicKey::from(&static_private).to_bytes();
+    let zeroed = without_reserved(packet);
+    if mac1(&static_public, &zeroed[..116]) != zeroed[116..132] {
+        return None;
+    }
+    let (chaining_key, hash_value) = initial_state(&static_public);
+    let initiator_ephemeral: [u8; 32] = packet[8..40].try_into().ok()?;
+    let [chaining_key] = kdf::<1>(&chaining_key, &initiator_ephemeral);
+    let hash_value = hash(&[&hash_value, &initiator_ephemeral]);
+
+    let [chaining_key, key] = kdf::<2>(
+        &chaining_key,
+        static_private
+            .diffie_hellman(&PublicKey::from(initiator_ephemeral))
+            .as_bytes(),
+    );
+    let initiator_static: [u8; 32] = open(&key, &packet[40..88], &hash_value)?.try_into().ok()?;
+    let hash_value = hash(&[&hash_value, &packet[40..88]]);
+
+    let [chaining_key, key] = kdf::<2>(
+        &chaining_key,
+        static_private
+            .diffie_hellman(&PublicKey::from(initiator_static))
+            .as_bytes(),
+    );
+    open(&key, &packet[88..116], &hash_value)?;
+    let hash_value = hash(&[&hash_value, &packet[88..116]]);
+
+    let ephemeral = StaticSecret::random_from_rng(OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral).to_bytes();
+    let [chaining_key] = kdf::<1>(&chaining_key, &ephemeral_public);
+    let hash_value = hash(&[&hash_value, &ephemeral_public]);
+    let [chaining_key] = kdf::<1>(
+        &chaining_key,
+        ephemeral
+            .diffie_hellman(&PublicKey::from(initiator_ephemeral))
+            .as_bytes(),
+    );
+    let [chaining_key] = kdf::<1>(
+        &chaining_key,
+        ephemeral
+            .diffie_hellman(&PublicKey::from(initiator_static))
+            .as_bytes(),
+    );
+    let [_, tau, key] = kdf::<3>(&chaining_key, &preshared_key.unwrap_or([0u8; 32]));
+    let hash_value = hash(&[&hash_value, &tau]);
+    let encrypted_nothing = seal(&key, &[], &hash_value);
+
+    let mut response = Vec::with_capacity(RESPONSE_LEN);
+    response.extend_from_slice(&header(MESSAGE_RESPONSE));
+    response.extend_from_slice(&sender_index.to_le_bytes());
+    response.extend_from_slice(&packet[4..8]);
+    response.extend_from_slice(&ephemeral_public);
+    response.extend_from_slice(&encrypted_nothing);
+    let mac = mac1(&initiator_static, &response);
+    response.extend_from_slice(&mac);
+    response.extend_from_slice(&[0u8; 16]);
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /* start、start+1……组成的32字节密钥 */
+    fn key_from(start: u8) -> [u8; 32] {
+        std::array::from_fn(|index| start + index as u8)
+    }
+
+    #[test]
+    fn initial_chaining_key_matches_wireguard() {
+        let (chaining_key, _) = initial_state(&[0u8; 32]);
+        assert_eq!(
+            hex(&chaining_key),
+            "60e26daef327efc02ec335e2a025d2d016eb4206f87277f52d38d1988b78cd36"
+        );
+    }
+
+    #[test]
+    fn public_key_matches_rfc7748() {
+        let private_key =
+            decode_key("dwdtCnMYpX08FsFyUbJmRd9ML4frwJkqsXf7pR25LCo=").expect("合法的密钥");
+        assert_eq!(
+            hex(&public_key_of(&private_key)),
+            "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"
+        );
+    }
+
+    /* 参考值按照WireGuard白皮书，用Python的cryptography库单独实现计算 */
+    #[test]
+    fn initiation_matches_reference_vector() {
+        let responder_public = public_key_of(&key_from(33));
+        assert_eq!(
+            hex(&responder_public),
+            "5869aff450549732cbaaed5e5df9b30a6da31cb0e5742bad5ad4a1a768f1a67b"
+        );
+        let timestamp = [
+            0x40, 0x00, 0x00, 0x00, 0x65, 0x53, 0xf1, 0x00, 0x07, 0x5b, 0xcd, 0x15,
+        ];
+        let initiation = Initiation::with_ephemeral(
+            &key_from(1),
+            &responder_public,
+            None,
+            Some([1, 2, 3]),
+            0x0102_0304,
+            StaticSecret::from(key_from(65)),
+            timestamp,
+        );
+        assert_eq!(
+            hex(&initiation.packet),
+            concat!(
+                "010102030403020164b101b1d0be5a8704bd078f9895001fc03e8e9f9522f188dd128d9846d48466158a0e4ca242d151",
+                "ca97ab90159a98b67e616625e68b4065d357376b6598e644ad7d678c0295d22de4cb43d5135581ed36346bfa7cf42a18",
+                "266235860f995f927e8292b2e718f970764f1048b16185e961f568e34694e3693ac007fa00000000000000000000000000000000"
+            )
+        );
+    }
+
+    #[test]
+    fn response_is_verified_with_matching_preshared_key() {
+        let responder_private = key_from(33);
+        let preshared_key = Some(key_from(100));
+        let initiation = Initiation::new(
+            &key_from(1),
+            &public_key_of(&responder_private),
+            preshared_key,
+            Some([1, 2, 3]),
+            7,
+        );
+        let response = respond(&responder_private, preshared_key, &initiation.packet, 9)
+            .expect("握手初始消息合法");
+        assert_eq!(response.len(), RESPONSE_LEN);
+        assert!(initiation.verify_response(&response));
+
+        let response =
+            respond(&responder_private, None, &initiation.packet, 9).expect("握手初始消息合法");
+        assert!(!initiation.verify_response(&response));
+    }
+
+    #[test]
+    fn initiation_for_another_key_is_ignored() {
+        let initiation = Initiation::new(&key_from(1), &public_key_of(&key_from(2)), None, None, 7);
+        assert!(respond(&key_from(33), None, &initiation.packet, 9).is_none());
+    }
+}
@@ -0,0 +1,217 @@
+use ipnet::IpNet;
+use rand::Rng;
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/* 一行CIDR或IP范围最多展开多少个地址，超过时必须加上sample=数量随机抽取 */
+pub const MAX_EXPANDED_ADDRESSES: u128 = 65536;
+
+/* ip.txt中的一段连续地址：CIDR网段(162.159.192.0/24)或者IP范围(188.114.96.0-188.114.96.50) */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    start: IpAddr,
+    count: u128,
+}
+
+fn to_number(address: IpAddr) -> u128 {
+    match address {
+        IpAddr::V4(v4) => u32::from(v4) as u128,
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+impl IpRange {
+    /*
+    解析CIDR网段或IP范围：
+        不像CIDR或IP范围的(比如普通的主机地址、域名)返回None；
+        看起来像但不合法的(前缀长度超出、范围的两端不是同一种地址、结束地址比开始地址小)返回错误原因。
+    */
+    pub fn parse(text: &str) -> Option<Result<Self, String>> {
+        if text.contains('/') {
+            let net = match text.parse::<IpNet>() {
+                Ok(net) => net,
+                Err(_) => return Some(Err(format!("“{}”不是合法的CIDR网段", text))),
+            };
+            let host_bits = (net.max_prefix_len() - net.prefix_len()) as u32;
+            return Some(Ok(IpRange {
+                start: net.network(),
+                count: 1u128.checked_shl(host_bits).unwrap_or(u128::MAX),
+            }));
+        }
+        // 域名中也可以有"-"，只有两边都是IP地址时才当作IP范围
+        let (start, end) = text.split_once('-')?;
+        let start = start.trim().parse::<IpAddr>().ok()?;
+        let end = end.trim().parse::<IpAddr>().ok()?;
+        if start.is_ipv4() != end.is_ipv4() {
+            return Some(Err(format!("“{}”两端的地址不是同一种IP地址", text)));
+        }
+        if to_number(end) < to_number(start) {
+            return Some(Err(format!("“{}”的结束地址比开始地址小", text)));
+        }
+        Some(Ok(IpRange {
+            start,
+            count: (to_number(end) - to_number(start)).saturating_add(1),
+        }))
+    }
+
+    /* 范围中地址的个数 */
+    pub fn len(&self) -> u128 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /* 范围中的第index个地址(从0开始) */
+    pub fn nth(&self, index: u128) -> IpAddr {
+        let number = to_number(self.start) + index;
+        match self.start {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(number as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(number)),
+        }
+    }
+
+    /*
+    展开成地址列表：
+        sample为None时展开全部地址，超过MAX_EXPANDED_ADDRESSES个时返回错误；
+        sample为Some(n)时随机抽取n个不重复的地址(地址不足n个时全部使用)，按地址从小到大排列。
+    */
+    pub fn addresses(&self, sample: Option<usize>) -> Result<Vec<IpAddr>, String> {
+        let wanted = match sample {
+            Some(sample) if (sample as u128) < self.count => sample,
+            _ if self.count > MAX_EXPANDED_ADDRESSES => {
+                return Err(format!(
+                    "范围中有{}个地址，超过了{}个的上限，请加上sample=数量随机抽取",
+                    self.count, MAX_EXPANDED_ADDRESSES
+                ))
+            }
+            _ => return Ok((0..self.count).map(|index| self.nth(index)).collect()),
+        };
+        if wanted as u128 > MAX_EXPANDED_ADDRESSES {
+            return Err(format!(
+                "sample={}超过了{}个的上限",
+                wanted, MAX_EXPANDED_ADDRESSES
+            ));
+        }
+        let mut rng = rand::thread_rng();
+        let mut picked = BTreeSet::new();
+        while picked.len() < wanted {
+            picked.insert(rng.gen_range(0..self.count));
+        }
+        Ok(picked.into_iter().map(|index| self.nth(index)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::{parse_endpoint_lines, RejectReason};
+
+    fn range(text: &str) -> IpRange {
+        IpRange::parse(text).unwrap().unwrap()
+    }
+
+    #[test]
+    fn parses_cidr_and_ranges() {
+        let v4 = range("162.159.192.0/24");
+        assert_eq!(v4.len(), 256);
+        assert_eq!(v4.nth(255).to_string(), "162.159.192.255");
+        // 网段的起点按前缀长度对齐
+        assert_eq!(
+            range("162.159.192.7/30").nth(0).to_string(),
+            "162.159.192.4"
+        );
+        assert_eq!(range("2606:4700:d0::/120").len(), 256);
+        assert_eq!(range("2606:4700:d0::/48").len(), 1u128 << 80);
+
+        let span = range("188.114.96.250-188.114.97.4");
+        assert_eq!(span.len(), 11);
+        assert_eq!(span.nth(10).to_string(), "188.114.97.4");
+        assert_eq!(range("188.114.96.1-188.114.96.1").len(), 1);
+    }
+
+    #[test]
+    fn hosts_and_domains_are_not_ranges() {
+        assert_eq!(IpRange::parse("162.159.192.1"), None);
+        assert_eq!(IpRange::parse("engage-1.cloudflareclient.com"), None);
+        assert_eq!(IpRange::parse("162.159.192.1-example.com"), None);
+    }
+
+    #[test]
+    fn invalid_ranges_are_errors() {
+        assert!(matches!(IpRange::parse("162.159.192.0/33"), Some(Err(_))));
+        assert!(matches!(
+            IpRange::parse("188.114.96.9-188.114.96.1"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            IpRange::parse("188.114.96.1-2606:4700:d0::1"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn expansion_is_capped() {
+        let limit = MAX_EXPANDED_ADDRESSES as usize;
+        assert_eq!(
+            range("162.159.0.0/16").addresses(None).unwrap().len(),
+            limit
+        );
+        assert!(range("162.158.0.0/15").addresses(None).is_err());
+        assert!(range("2606:4700:d0::/48").addresses(None).is_err());
+        assert!(range("2606:4700:d0::/48")
+            .addresses(Some(limit + 1))
+            .is_err());
+    }
+
+    #[test]
+    fn sample_picks_distinct_sorted_addresses() {
+        let picked = range("2606:4700:d0::/48").addresses(Some(50)).unwrap();
+        assert_eq!(picked.len(), 50);
+        assert!(picked.windows(2).all(|pair| pair[0] < pair[1]));
+        // 地址不足sample个时全部使用
+        assert_eq!(
+            range("162.159.192.0/30").addresses(Some(10)).unwrap().len(),
+            4
+        );
+    }
+
+    #[test]
+    fn ip_txt_lines_expand_with_and_without_ports() {
+        // IPv6网段的前缀长度写在中括号外面
+        let list = parse_endpoint_lines("[2606:4700:d0::]/120:2408\n", &[]);
+        assert_eq!(list.endpoints.len(), 256);
+        assert!(list.endpoints.iter().all(|endpoint| endpoint.port == 2408));
+        assert_eq!(list.endpoints[1].host, "2606:4700:d0::1");
+
+        let list = parse_endpoint_lines("188.114.96.1-188.114.96.3 2408\n", &[]);
+        assert_eq!(list.endpoints.len(), 3);
+
+        // 没有端口的网段跟每个端口组合
+        let list = parse_endpoint_lines("162.159.192.0/30\n", &[2408, 500]);
+        assert_eq!(list.endpoints.len(), 8);
+        let list = parse_endpoint_lines("162.159.192.0/30\n", &[]);
+        assert_eq!(list.rejected[0].reason, RejectReason::MissingPort);
+    }
+
+    #[test]
+    fn ip_txt_sample_and_cap() {
+        let list = parse_endpoint_lines("162.159.192.0/24:2408 sample=5\n", &[]);
+        assert_eq!(list.endpoints.len(), 5);
+        let list = parse_endpoint_lines("162.158.0.0/15:2408 sample=5\n", &[]);
+        assert_eq!(list.endpoints.len(), 5);
+
+        let list = parse_endpoint_lines("162.158.0.0/15:2408\n", &[]);
+        assert!(list.endpoints.is_empty());
+        assert!(matches!(
+            list.rejected[0].reason,
+            RejectReason::InvalidRange(_)
+        ));
+        let list = parse_endpoint_lines("162.159.192.0/24:2408 sample=0\n", &[]);
+        assert!(matches!(
+            list.rejected[0].reason,
+            RejectReason::InvalidRange(_)
+        ));
+    }
+}
@@ -0,0 +1,45 @@
+/* WireGuard配置文件转换为nekoray节点链接的核心功能，app1和app2两个程序共用 */
+pub mod candidates;
+pub mod clash;
+pub mod config;
+pub mod endpoint;
+pub mod error;
+pub mod format;
+pub mod geoip;
+pub mod handshake;
+pub mod ip_range;
+pub mod naming;
+pub mod nekoray;
+pub mod networkd;
+pub mod node;
+pub mod openwrt;
+pub mod output;
+pub mod paths;
+pub mod ports;
+pub mod probe;
+pub mod reserved;
+pub mod scan_result;
+pub mod settings;
+pub mod singbox;
+pub mod wg_quick;
+pub mod wireguard_uri;
+pub mod xray;
+
+pub use candidates::{warp_candidates, CandidateFamily, CandidateOptions};
+pub use clash::generate_clash_config;
+pub use config::WireGuardConfig;
+pub use endpoint::Endpoint;
+pub use error::Error;
+pub use format::OutputFormat;
+pub use geoip::{GeoInfo, GeoIpDatabase};
+pub use naming::{NameTemplate, NodeNaming};
+pub use nekoray::{decode_nekoray_link, generate_nekoray_node, render_nekoray_link, NekorayFormat};
+pub use networkd::generate_networkd_files;
+pub use node::{plan_nodes, NodeOptions, NodeSpec};
+pub use openwrt::generate_openwrt_config;
+pub use probe::{probe_endpoints, LoopbackResponder, ProbeKeys, ProbeOptions, ProbeResult};
+pub use reserved::Reserved;
+pub use singbox::{generate_singbox_config, SingBoxTarget};
+pub use wg_quick::{generate_wg_quick_files, render_wg_quick};
+pub use wireguard_uri::{generate_wireguard_uris, parse_wireguard_uri, render_wireguard_uri};
+pub use xray::{generate_xray_config, XrayLayout};
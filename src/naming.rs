@@ -0,0 +1,175 @@
+use crate::endpoint::{Endpoint, EndpointMetrics};
+use crate::geoip::GeoInfo;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/* 节点名称模板中可以使用的字段 */
+const FIELDS: [&str; 11] = [
+    "prefix", "host", "port", "index", "family", "latency", "loss", "country", "flag", "asn", "mtu",
+];
+
+/* 模板中的一段：原样输出的文字，或者{字段} */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Field(&'static str),
+}
+
+/*
+节点名称模板，比如"{prefix}_{host}:{port}"、"{country}-{latency}ms-{index}"：
+    {prefix}：节点名称的前缀(不带"_")；{host}、{port}：Endpoint的主机地址和端口；
+    {index}：节点的序号(从1开始)；{family}：IPv4、IPv6或者域名；
+    {latency}、{loss}：扫描器测出的延迟(毫秒)和丢包率(百分比)；
+    {country}、{flag}、{asn}：GeoIP数据库中查到的国家或地区代码、旗帜emoji和自治系统编号(比如AS13335)；
+    {mtu}：节点使用的MTU值。没有值的字段替换成空字符串。
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl FromStr for NameTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("节点名称模板“{}”中的“{{”没有对应的“}}”", s))?;
+            let field = &rest[start + 1..start + end];
+            let field = FIELDS
+                .iter()
+                .find(|name| name.eq_ignore_ascii_case(field.trim()))
+                .ok_or_else(|| {
+                    format!(
+                        "节点名称模板中没有{{{}}}字段，可以使用的字段：{}",
+                        field,
+                        FIELDS.map(|name| format!("{{{}}}", name)).join("、")
+                    )
+                })?;
+            segments.push(Segment::Field(field));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        if segments.is_empty() {
+            return Err("节点名称模板不能为空".to_string());
+        }
+        Ok(NameTemplate {
+            source: s.to_string(),
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/* 数值去掉末尾多余的0，比如95.00 => 95、12.50 => 12.5 */
+fn format_number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/* 主机地址的类型 */
+fn family(host: &str) -> &'static str {
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => "IPv4",
+        Ok(IpAddr::V6(_)) => "IPv6",
+        Err(_) => "域名",
+    }
+}
+
+impl NameTemplate {
+    /* 模板中是否用到了某个字段 */
+    pub fn uses(&self, field: &str) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Field(name) if *name == field))
+    }
+}
+
+/*
+生成节点名称使用的信息：前缀(不为空时已经带有"_")、名称模板，以及ip.txt中Endpoint的测量值和地理信息；
+没有模板时，节点名称就是"前缀 + 主机地址:端口"
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeNaming {
+    pub prefix: String,
+    pub template: Option<NameTemplate>,
+    pub metrics: HashMap<Endpoint, EndpointMetrics>,
+    pub geo: HashMap<Endpoint, GeoInfo>,
+}
+
+impl NodeNaming {
+    /* 只有前缀，没有模板和Endpoint的其他信息 */
+    pub fn new(prefix: &str) -> Self {
+        NodeNaming {
+            prefix: prefix.to_string(),
+            ..NodeNaming::default()
+        }
+    }
+
+    /* 第index个节点(从1开始)的名称，去掉首尾的空白(字段没有值时可能留下空格) */
+    pub fn name_for(&self, endpoint: &Endpoint, index: usize, mtu: u16) -> String {
+        let template = match &self.template {
+            Some(template) => template,
+            None => return format!("{}{}", self.prefix, endpoint),
+        };
+        let metrics = self.metrics.get(endpoint).copied().unwrap_or_default();
+        let geo = self.geo.get(endpoint).cloned().unwrap_or_default();
+        template
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.clone(),
+                Segment::Field(field) => match *field {
+                    "prefix" => self.prefix.trim_end_matches('_').to_string(),
+                    "host" => endpoint.host_name(),
+                    "port" => endpoint.port.to_string(),
+                    "index" => index.to_string(),
+                    "family" => family(&endpoint.host).to_string(),
+                    "latency" => metrics.latency.map(format_number).unwrap_or_default(),
+                    "loss" => metrics.loss.map(format_number).unwrap_or_default(),
+                    "country" => geo.country.clone().unwrap_or_default(),
+                    "flag" => geo.flag().unwrap_or_default(),
+                    "asn" => geo.asn.map(|asn| format!("AS{}", asn)).unwrap_or_default(),
+                    "mtu" => mtu.to_string(),
+                    _ => String::new(),
+                },
+            })
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+}
+
+/* 节点名称跟前面的重复时，在后面加上"_2"、"_3"……，让NekoBox中的分组容易分辨 */
+pub fn unique_names(names: Vec<String>) -> Vec<String> {
+    let mut used: HashMap<String, usize> = HashMap::new();
+    let mut result = Vec::with_capacity(names.len());
+    for name in names {
+        let mut candidate = name.clone();
+        let mut counter = used.get(&name).copied().unwrap_or(1);
+        while used.contains_key(&candidate) {
+            counter += 1;
+            candidate = format!("{}_{}", name, counter);
+        }
+        used.insert(name, counter);
+        used.entry(candidate.clone()).or_insert(1);
+        result.push(candidate);
+    }
+    result
+}
@@ -0,0 +1,289 @@
+use crate::config::{Interface, Peer, WireGuardConfig};
+use crate::endpoint::{is_valid_host, Endpoint};
+use crate::error::Error;
+use crate::naming::NodeNaming;
+use crate::node::{plan_nodes, NodeOptions};
+use crate::reserved::Reserved;
+use crate::singbox::WireGuardOutbound;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+pub const NEKORAY_CUSTOM_SCHEME: &str = "nekoray://custom#";
+pub const NEKORAY_WIREGUARD_SCHEME: &str = "nekoray://wireguard#";
+
+/* 生成哪种nekoray链接：自定义核心(sing-box出站放在cs字段中)，或者新版NekoBox原生的WireGuard节点 */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NekorayFormat {
+    #[default]
+    Custom,
+    Native,
+}
+
+impl FromStr for NekorayFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "custom" | "nekoray" => Ok(NekorayFormat::Custom),
+            "native" | "nekobox" => Ok(NekorayFormat::Native),
+            _ => Err(format!(
+                "不支持的链接格式“{}”，可选的值：custom、native",
+                s.trim()
+            )),
+        }
+    }
+}
+
+/* nekoray的“自定义核心”节点(custom bean)，cs字段是sing-box出站的JSON字符串 */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NekorayBean {
+    #[serde(rename = "_v")]
+    pub version: i32,
+    pub addr: String,
+    pub cmd: Vec<String>,
+    pub core: String,
+    pub cs: String,
+    pub mapping_port: u16,
+    pub name: String,
+    pub port: u16,
+    pub socks_port: u16,
+}
+
+impl NekorayBean {
+    /* 把sing-box出站包装成nekoray的自定义节点，其他字段使用NekoBox的默认值 */
+    pub fn custom(name: &str, outbound: &WireGuardOutbound) -> Self {
+        NekorayBean {
+            version: 0,
+            addr: "127.0.0.1".to_string(),
+            cmd: vec![String::new()],
+            core: "internal".to_string(),
+            cs: serde_json::to_string_pretty(outbound).expect("无法序列化sing-box出站"),
+            mapping_port: 0,
+            name: name.to_string(),
+            port: 1080,
+            socks_port: 0,
+        }
+    }
+
+    /* 序列化为JSON后base64编码，生成nekoray链接 */
+    pub fn to_link(&self) -> String {
+        let json = serde_json::to_string(self).expect("无法序列化nekoray节点");
+        format!("{}{}", NEKORAY_CUSTOM_SCHEME, base64::encode(json))
+    }
+
+    /* 解码nekoray链接：去掉"nekoray://custom#"前缀，base64解码后解析JSON */
+    pub fn from_link(link: &str) -> Result<Self, Error> {
+        let encoded = link
+            .trim()
+            .strip_prefix(NEKORAY_CUSTOM_SCHEME)
+            .ok_or_else(|| Error::InvalidLink(format!("不是以{}开头", NEKORAY_CUSTOM_SCHEME)))?;
+        let json = base64::decode(encoded.trim())
+            .map_err(|err| Error::InvalidLink(format!("base64解码失败：{}", err)))?;
+        serde_json::from_slice(&json)
+            .map_err(|err| Error::InvalidLink(format!("节点JSON解析失败：{}", err)))
+    }
+
+    /* 解析cs字段中的sing-box出站 */
+    pub fn outbound(&self) -> Result<WireGuardOutbound, Error> {
+        serde_json::from_str(&self.cs)
+            .map_err(|err| Error::InvalidLink(format!("cs字段不是wireguard出站：{}", err)))
+    }
+}
+
+/* NekoBox原生的WireGuard节点(wireguard bean)，在NekoBox界面上能直接看到地址、端口和密钥 */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NekoboxWireGuardBean {
+    #[serde(rename = "_v")]
+    pub version: i32,
+    pub addr: String,
+    pub port: u16,
+    pub name: String,
+    pub private_key: String,
+    pub public_key: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub pre_shared_key: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reserved: Vec<u8>,
+    pub persistent_keepalive: u16,
+    pub local_address: Vec<String>,
+    pub mtu: u16,
+    pub use_system_interface: bool,
+    pub worker_count: u16,
+}
+
+impl NekoboxWireGuardBean {
+    /* 以WireGuard配置文件的参数为基准，使用指定的[Peer]和Endpoint生成原生节点 */
+    pub fn new(
+        config: &WireGuardConfig,
+        peer: &Peer,
+        endpoint: &Endpoint,
+        name: &str,
+        options: &NodeOptions,
+    ) -> Self {
+        NekoboxWireGuardBean {
+            version: 0,
+            addr: endpoint.host.clone(),
+            port: endpoint.port,
+            name: name.to_string(),
+            private_key: config.interface.private_key.clone(),
+            public_key: peer.public_key.clone(),
+            pre_shared_key: peer.preshared_key.clone().unwrap_or_default(),
+            reserved: options
+                .reserved_for(config, peer)
+                .map(|reserved| reserved.0.to_vec())
+                .unwrap_or_default(),
+            persistent_keepalive: peer.persistent_keepalive.unwrap_or(0),
+            local_address: config.interface.addresses.clone(),
+            mtu: options.mtu_for(config),
+            use_system_interface: false,
+            worker_count: 0,
+        }
+    }
+
+    /* 序列化为JSON后base64编码，生成"nekoray://wireguard#"链接 */
+    pub fn to_link(&self) -> String {
+        let json = serde_json::to_string(self).expect("无法序列化nekoray节点");
+        format!("{}{}", NEKORAY_WIREGUARD_SCHEME, base64::encode(json))
+    }
+
+    /* 解码"nekoray://wireguard#"链接 */
+    pub fn from_link(link: &str) -> Result<Self, Error> {
+        let encoded = link
+            .trim()
+            .strip_prefix(NEKORAY_WIREGUARD_SCHEME)
+            .ok_or_else(|| Error::InvalidLink(format!("不是以{}开头", NEKORAY_WIREGUARD_SCHEME)))?;
+        let json = base64::decode(encoded.trim())
+            .map_err(|err| Error::InvalidLink(format!("base64解码失败：{}", err)))?;
+        serde_json::from_slice(&json)
+            .map_err(|err| Error::InvalidLink(format!("节点JSON解析失败：{}", err)))
+    }
+
+    /* 把原生节点还原成WireGuard配置文件 */
+    pub fn to_config(&self) -> Result<(WireGuardConfig, Endpoint), Error> {
+        if !is_valid_host(&self.addr) {
+            return Err(Error::InvalidValue {
+                key: "addr",
+                value: self.addr.clone(),
+            });
+        }
+        let endpoint = Endpoint {
+            host: self.addr.clone(),
+            port: self.port,
+        };
+        let reserved = match self.reserved.as_slice() {
+            [] => None,
+            [a, b, c] => Some(Reserved([*a, *b, *c])),
+            _ => {
+                return Err(Error::InvalidValue {
+                    key: "reserved",
+                    value: format!("{:?}", self.reserved),
+                })
+            }
+        };
+        let config = WireGuardConfig {
+            interface: Interface {
+                private_key: self.private_key.clone(),
+                addresses: self.local_address.clone(),
+                mtu: Some(self.mtu).filter(|mtu| *mtu > 0),
+                ..Interface::default()
+            },
+            peers: vec![Peer {
+                public_key: self.public_key.clone(),
+                preshared_key: Some(self.pre_shared_key.clone()).filter(|key| !key.is_empty()),
+                allowed_ips: vec!["0.0.0.0/0".to_string(), "::/0".to_string()],
+                endpoint: Some(endpoint.clone()),
+                persistent_keepalive: Some(self.persistent_keepalive)
+                    .filter(|interval| *interval > 0),
+                reserved,
+                ..Peer::default()
+            }],
+            sections: Vec::new(),
+        };
+        Ok((config, endpoint))
+    }
+}
+
+/* 从nekoray链接还原出来的节点：节点名称、WireGuard配置（[Peer]的Endpoint就是节点的地址） */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedNode {
+    pub name: String,
+    pub config: WireGuardConfig,
+    pub endpoint: Endpoint,
+}
+
+/* generate_nekoray_node的逆操作：把nekoray链接(自定义核心或原生WireGuard节点)还原成WireGuard配置 */
+pub fn decode_nekoray_link(link: &str) -> Result<DecodedNode, Error> {
+    if link.trim().starts_with(NEKORAY_WIREGUARD_SCHEME) {
+        let bean = NekoboxWireGuardBean::from_link(link)?;
+        let (config, endpoint) = bean.to_config()?;
+        return Ok(DecodedNode {
+            name: bean.name,
+            config,
+            endpoint,
+        });
+    }
+    let bean = NekorayBean::from_link(link)?;
+    let outbound = bean.outbound()?;
+    if outbound.kind != "wireguard" {
+        return Err(Error::InvalidLink(format!(
+            "出站类型是{}，不是wireguard",
+            outbound.kind
+        )));
+    }
+    let (config, endpoint) = outbound.to_config()?;
+    Ok(DecodedNode {
+        name: bean.name,
+        config,
+        endpoint,
+    })
+}
+
+/* 以WireGuard配置文件的参数为基准，使用指定的[Peer]生成一条nekoray链接；options中没有指定的参数使用配置文件的值 */
+pub fn render_nekoray_link(
+    config: &WireGuardConfig,
+    peer: &Peer,
+    endpoint: &Endpoint,
+    name: &str,
+    options: &NodeOptions,
+) -> String {
+    match options.nekoray_format {
+        NekorayFormat::Custom => {
+            let outbound = WireGuardOutbound::new(config, peer, endpoint, "proxy", options);
+            let mut bean = NekorayBean::custom(name, &outbound);
+            if let Some(port) = options.nekoray_port {
+                bean.port = port;
+            }
+            if let Some(port) = options.socks_port {
+                bean.socks_port = port;
+            }
+            bean.to_link()
+        }
+        NekorayFormat::Native => {
+            NekoboxWireGuardBean::new(config, peer, endpoint, name, options).to_link()
+        }
+    }
+}
+
+/* 批量将ip.txt中的Endpoint写入到nekoray链接中，不合法的行直接跳过；有多个[Peer]时每个[Peer]各生成节点；每生成一个节点调用一次progress */
+pub fn generate_nekoray_node<F>(
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    cross: bool,
+    mut progress: F,
+) -> Vec<String>
+where
+    F: FnMut(&str, &str),
+{
+    let mut nekoray_node_vec: Vec<String> = Vec::new();
+    for spec in plan_nodes(config, endpoints, cross, naming, options) {
+        let nekoray_node =
+            render_nekoray_link(config, spec.peer, &spec.endpoint, &spec.name, options);
+        progress(&spec.name, "Nekoray链接");
+        nekoray_node_vec.push(nekoray_node);
+    }
+    nekoray_node_vec
+}
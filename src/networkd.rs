@@ -0,0 +1,110 @@
+use crate::config::{Peer, WireGuardConfig};
+use crate::endpoint::Endpoint;
+use crate::naming::NodeNaming;
+use crate::node::{plan_nodes, NodeOptions};
+use crate::openwrt::INTERFACE_PREFIX;
+use std::fmt::Write;
+
+/* 生成systemd-networkd的.netdev文件：网卡、私钥和peer */
+pub fn render_netdev(
+    config: &WireGuardConfig,
+    peer: &Peer,
+    endpoint: &Endpoint,
+    interface_name: &str,
+    description: &str,
+    options: &NodeOptions,
+) -> String {
+    let mut netdev = String::new();
+    let _ = writeln!(netdev, "[NetDev]");
+    let _ = writeln!(netdev, "Name={}", interface_name);
+    let _ = writeln!(netdev, "Kind=wireguard");
+    let _ = writeln!(netdev, "MTUBytes={}", options.mtu_for(config));
+    let _ = writeln!(netdev, "Description={}", description);
+    let _ = writeln!(netdev, "\n[WireGuard]");
+    let _ = writeln!(netdev, "PrivateKey={}", config.interface.private_key);
+    let _ = writeln!(netdev, "\n[WireGuardPeer]");
+    let _ = writeln!(netdev, "PublicKey={}", peer.public_key);
+    if let Some(key) = &peer.preshared_key {
+        let _ = writeln!(netdev, "PresharedKey={}", key);
+    }
+    if peer.allowed_ips.is_empty() {
+        let _ = writeln!(netdev, "AllowedIPs=0.0.0.0/0, ::/0");
+    } else {
+        let _ = writeln!(netdev, "AllowedIPs={}", peer.allowed_ips.join(", "));
+    }
+    let _ = writeln!(netdev, "Endpoint={}", endpoint);
+    if let Some(interval) = peer.persistent_keepalive {
+        let _ = writeln!(netdev, "PersistentKeepalive={}", interval);
+    }
+    netdev
+}
+
+/* 生成systemd-networkd的.network文件：网卡的地址和DNS */
+pub fn render_network(config: &WireGuardConfig, interface_name: &str) -> String {
+    let mut network = String::new();
+    let _ = writeln!(network, "[Match]");
+    let _ = writeln!(network, "Name={}", interface_name);
+    let _ = writeln!(network, "\n[Network]");
+    for address in &config.interface.addresses {
+        let _ = writeln!(network, "Address={}", address);
+    }
+    for dns in &config.interface.dns {
+        let _ = writeln!(network, "DNS={}", dns);
+    }
+    network
+}
+
+/* 文件名是不是生成的networkd配置文件(比如wg0.netdev、wg0.network) */
+pub fn is_networkd_file(file_name: &str) -> bool {
+    let stem = match file_name.rsplit_once('.') {
+        Some((stem, "netdev" | "network")) => stem,
+        _ => return false,
+    };
+    stem.strip_prefix(INTERFACE_PREFIX)
+        .is_some_and(|index| !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()))
+}
+
+/*
+批量将ip.txt中的Endpoint生成systemd-networkd的配置文件，返回(文件名, 文件内容)：
+    每个Endpoint一对wg序号.netdev和wg序号.network文件，复制到/etc/systemd/network/目录中使用；
+    每生成一对文件调用一次progress(节点名称, 生成的文件名)
+*/
+pub fn generate_networkd_files<F>(
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    cross: bool,
+    mut progress: F,
+) -> Vec<(String, String)>
+where
+    F: FnMut(&str, &str),
+{
+    let mut files = Vec::new();
+    for (index, spec) in plan_nodes(config, endpoints, cross, naming, options)
+        .iter()
+        .enumerate()
+    {
+        let interface_name = format!("{}{}", INTERFACE_PREFIX, index);
+        files.push((
+            format!("{}.netdev", interface_name),
+            render_netdev(
+                config,
+                spec.peer,
+                &spec.endpoint,
+                &interface_name,
+                &spec.name,
+                options,
+            ),
+        ));
+        files.push((
+            format!("{}.network", interface_name),
+            render_network(config, &interface_name),
+        ));
+        progress(
+            &spec.name,
+            &format!("{0}.netdev、{0}.network", interface_name),
+        );
+    }
+    files
+}
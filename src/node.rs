@@ -0,0 +1,108 @@
+use crate::config::{Peer, WireGuardConfig};
+use crate::endpoint::Endpoint;
+use crate::naming::{unique_names, NodeNaming};
+use crate::nekoray::NekorayFormat;
+use crate::reserved::Reserved;
+
+/* 没有指定MTU值，配置文件中也没有MTU值时，使用的默认值 */
+pub const DEFAULT_MTU: u16 = 1408;
+
+/* 生成节点时，用户在命令行或输入框中指定的参数，优先于配置文件中的值 */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeOptions {
+    pub mtu: Option<u16>,
+    pub reserved: Option<Reserved>,
+    pub nekoray_format: NekorayFormat,
+    // nekoray自定义核心节点的端口(port)和socks端口(socks_port)，不指定时使用NekoBox的默认值
+    pub nekoray_port: Option<u16>,
+    pub socks_port: Option<u16>,
+}
+
+impl NodeOptions {
+    /* 实际使用的MTU值：用户指定的 > 配置文件的 > 默认值 */
+    pub fn mtu_for(&self, config: &WireGuardConfig) -> u16 {
+        self.mtu.or(config.interface.mtu).unwrap_or(DEFAULT_MTU)
+    }
+
+    /* 实际使用的reserved：用户指定的 > [Peer]节的 > [Interface]节的 */
+    pub fn reserved_for(&self, config: &WireGuardConfig, peer: &Peer) -> Option<Reserved> {
+        self.reserved
+            .or(peer.reserved)
+            .or(config.interface.reserved)
+    }
+}
+
+/* 一个要生成的节点：使用哪个[Peer]、连接哪个Endpoint、节点叫什么名字 */
+#[derive(Debug, Clone)]
+pub struct NodeSpec<'a> {
+    pub peer: &'a Peer,
+    pub endpoint: Endpoint,
+    pub name: String,
+}
+
+/*
+根据配置文件中的[Peer]和ip.txt中的Endpoint，决定要生成哪些节点：
+    1、只有一个[Peer]，或者cross为true时，每个[Peer]都跟ip.txt中的每个Endpoint组合一次；
+    2、有多个[Peer]时，每个[Peer]使用自己的Endpoint各生成一个节点（没有Endpoint的[Peer]跳过）；
+    3、ip.txt中没有Endpoint时，同样使用每个[Peer]自己的Endpoint。
+节点名称由naming决定，有多个[Peer]时，节点名称后面加上"_peer序号"；名称仍然重复时再加上"_2"、"_3"……
+*/
+pub fn plan_nodes<'a>(
+    config: &'a WireGuardConfig,
+    endpoints: &[Endpoint],
+    cross: bool,
+    naming: &NodeNaming,
+    options: &NodeOptions,
+) -> Vec<NodeSpec<'a>> {
+    let multi_peer = config.peers.len() > 1;
+    let mut pairs: Vec<(usize, &'a Peer, Endpoint)> = Vec::new();
+    if !endpoints.is_empty() && (cross || !multi_peer) {
+        for (index, peer) in config.peers.iter().enumerate() {
+            for endpoint in endpoints {
+                pairs.push((index, peer, endpoint.clone()));
+            }
+        }
+    } else {
+        for (index, peer) in config.peers.iter().enumerate() {
+            if let Some(endpoint) = &peer.endpoint {
+                pairs.push((index, peer, endpoint.clone()));
+            }
+        }
+    }
+
+    let mtu = options.mtu_for(config);
+    let names = pairs
+        .iter()
+        .enumerate()
+        .map(|(position, (index, _, endpoint))| {
+            let name = naming.name_for(endpoint, position + 1, mtu);
+            if multi_peer {
+                format!("{}_peer{}", name, index + 1)
+            } else {
+                name
+            }
+        })
+        .collect();
+    pairs
+        .into_iter()
+        .zip(unique_names(names))
+        .map(|((_, peer, endpoint), name)| NodeSpec {
+            peer,
+            endpoint,
+            name,
+        })
+        .collect()
+}
+
+/* 把节点名称转换成可以作为文件名的字符串，字母、数字、"-"、"."以外的字符都换成"_" */
+pub fn file_safe_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,99 @@
+use crate::config::{Peer, WireGuardConfig};
+use crate::endpoint::Endpoint;
+use crate::naming::NodeNaming;
+use crate::node::{plan_nodes, NodeOptions};
+use std::fmt::Write;
+
+/* OpenWrt和systemd-networkd中WireGuard网卡名称的前缀，后面加上序号(wg0、wg1……) */
+pub const INTERFACE_PREFIX: &str = "wg";
+
+/* UCI的值使用单引号括起来，值中的单引号要转义 */
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/*
+生成OpenWrt的/etc/config/network中的一个WireGuard网卡和它的peer：
+    同一个网卡中不能有公钥相同的peer，所以每个Endpoint单独使用一个网卡；
+    route_allowed_ips设为0，避免多个网卡都添加默认路由，需要时在LuCI中手动打开
+*/
+pub fn render_openwrt_interface(
+    config: &WireGuardConfig,
+    peer: &Peer,
+    endpoint: &Endpoint,
+    interface_name: &str,
+    description: &str,
+    options: &NodeOptions,
+) -> String {
+    let interface = &config.interface;
+    let mut uci = String::new();
+    let _ = writeln!(uci, "config interface {}", quote(interface_name));
+    let _ = writeln!(uci, "\toption proto 'wireguard'");
+    let _ = writeln!(
+        uci,
+        "\toption private_key {}",
+        quote(&interface.private_key)
+    );
+    for address in &interface.addresses {
+        let _ = writeln!(uci, "\tlist addresses {}", quote(address));
+    }
+    for dns in &interface.dns {
+        let _ = writeln!(uci, "\tlist dns {}", quote(dns));
+    }
+    let _ = writeln!(uci, "\toption mtu '{}'", options.mtu_for(config));
+    let _ = writeln!(uci);
+
+    let _ = writeln!(uci, "config wireguard_{}", interface_name);
+    let _ = writeln!(uci, "\toption description {}", quote(description));
+    let _ = writeln!(uci, "\toption public_key {}", quote(&peer.public_key));
+    if let Some(key) = &peer.preshared_key {
+        let _ = writeln!(uci, "\toption preshared_key {}", quote(key));
+    }
+    let allowed_ips = if peer.allowed_ips.is_empty() {
+        vec!["0.0.0.0/0".to_string(), "::/0".to_string()]
+    } else {
+        peer.allowed_ips.clone()
+    };
+    for allowed_ip in &allowed_ips {
+        let _ = writeln!(uci, "\tlist allowed_ips {}", quote(allowed_ip));
+    }
+    let _ = writeln!(uci, "\toption endpoint_host {}", quote(&endpoint.host));
+    let _ = writeln!(uci, "\toption endpoint_port '{}'", endpoint.port);
+    if let Some(interval) = peer.persistent_keepalive {
+        let _ = writeln!(uci, "\toption persistent_keepalive '{}'", interval);
+    }
+    let _ = writeln!(uci, "\toption route_allowed_ips '0'");
+    uci
+}
+
+/* 批量将ip.txt中的Endpoint生成OpenWrt的UCI配置，追加到路由器的/etc/config/network文件中使用，每生成一个网卡调用一次progress(节点名称, 网卡) */
+pub fn generate_openwrt_config<F>(
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    cross: bool,
+    mut progress: F,
+) -> Option<String>
+where
+    F: FnMut(&str, &str),
+{
+    let specs = plan_nodes(config, endpoints, cross, naming, options);
+    if specs.is_empty() {
+        return None;
+    }
+    let mut stanzas = Vec::new();
+    for (index, spec) in specs.iter().enumerate() {
+        let interface_name = format!("{}{}", INTERFACE_PREFIX, index);
+        stanzas.push(render_openwrt_interface(
+            config,
+            spec.peer,
+            &spec.endpoint,
+            &interface_name,
+            &spec.name,
+            options,
+        ));
+        progress(&spec.name, &format!("OpenWrt网卡{}", interface_name));
+    }
+    Some(stanzas.join("\n").trim_end().to_string())
+}
@@ -0,0 +1,163 @@
+use crate::nekoray::decode_nekoray_link;
+use crate::paths::is_stdio;
+use crate::wireguard_uri::parse_wireguard_uri;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use tempfile::NamedTempFile;
+
+/* 输出文件已经存在时怎么处理：覆盖、追加到末尾，或者保留已有的链接只添加新的Endpoint */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WriteMode {
+    #[default]
+    Overwrite,
+    Append,
+    Merge,
+}
+
+impl FromStr for WriteMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "overwrite" => Ok(WriteMode::Overwrite),
+            "append" => Ok(WriteMode::Append),
+            "merge" => Ok(WriteMode::Merge),
+            _ => Err(format!(
+                "不支持的写入方式“{}”，可选的值：overwrite、append、merge",
+                s.trim()
+            )),
+        }
+    }
+}
+
+/* 写入的结果：写入了几行，merge时跳过了几个已有的节点 */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteSummary {
+    pub written: usize,
+    pub skipped: usize,
+}
+
+/* 判断两条链接是不是同一个节点：使用[Peer]的公钥和Endpoint，无法解析的链接使用整行内容 */
+fn link_key(line: &str) -> String {
+    let line = line.trim();
+    if let Ok(node) = decode_nekoray_link(line) {
+        let public_key = node
+            .config
+            .peers
+            .first()
+            .map(|peer| peer.public_key.as_str());
+        return format!("{}@{}", public_key.unwrap_or_default(), node.endpoint);
+    }
+    if let Ok((_, config)) = parse_wireguard_uri(line) {
+        if let Some(peer) = config.peers.first() {
+            if let Some(endpoint) = &peer.endpoint {
+                return format!("{}@{}", peer.public_key, endpoint);
+            }
+        }
+    }
+    line.to_string()
+}
+
+/* 先写入同一目录中的临时文件，写完后再改名为输出文件，程序中断时不会留下只写了一半的文件 */
+fn write_atomic(path: &str, contents: &str) -> io::Result<()> {
+    let dir = match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut file = NamedTempFile::new_in(dir)?;
+    // 覆盖已有的文件时保留原来的权限，新文件使用临时文件的权限(只有当前用户可以读写，文件中有私钥)
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(file.path(), metadata.permissions())?;
+    }
+    file.write_all(contents.as_bytes())?;
+    file.flush()?;
+    file.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+/*
+把生成的内容按行写入输出文件，路径为"-"时写到标准输出：
+    1、overwrite：替换原来的文件；
+    2、append：追加到原来的内容后面；
+    3、merge：保留原来的链接，只添加原来没有的节点(同一个[Peer]公钥和Endpoint算同一个节点)
+*/
+pub fn write_lines(path: &str, lines: &[String], mode: WriteMode) -> io::Result<WriteSummary> {
+    if is_stdio(path) {
+        let mut stdout = io::stdout().lock();
+        for line in lines {
+            writeln!(stdout, "{}", line)?;
+        }
+        stdout.flush()?;
+        return Ok(WriteSummary {
+            written: lines.len(),
+            skipped: 0,
+        });
+    }
+    let existing = match mode {
+        WriteMode::Overwrite => String::new(),
+        WriteMode::Append | WriteMode::Merge => match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        },
+    };
+    let new_lines: Vec<&String> = match mode {
+        WriteMode::Merge => {
+            let mut seen: HashSet<String> = existing
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(link_key)
+                .collect();
+            lines
+                .iter()
+                .filter(|line| seen.insert(link_key(line)))
+                .collect()
+        }
+        _ => lines.iter().collect(),
+    };
+    let mut contents = existing;
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for line in &new_lines {
+        contents.push_str(line);
+        contents.push('\n');
+    }
+    write_atomic(path, &contents)?;
+    Ok(WriteSummary {
+        written: new_lines.len(),
+        skipped: lines.len() - new_lines.len(),
+    })
+}
+
+/*
+每个节点一个文件的输出格式(比如wg-quick配置)，把所有文件写入输出目录，每个文件都先写临时文件再改名；
+generated判断文件名是不是本程序以前生成的，这次没有再生成的先删除，不留下已经不用的节点
+*/
+pub fn write_files<F>(dir: &str, files: &[(String, String)], generated: F) -> io::Result<()>
+where
+    F: Fn(&str) -> bool,
+{
+    fs::create_dir_all(dir)?;
+    let current: HashSet<&str> = files
+        .iter()
+        .map(|(file_name, _)| file_name.as_str())
+        .collect();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_file()
+            && generated(&file_name)
+            && !current.contains(file_name.as_str())
+        {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    for (file_name, contents) in files {
+        write_atomic(&Path::new(dir).join(file_name).to_string_lossy(), contents)?;
+    }
+    Ok(())
+}
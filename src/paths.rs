@@ -0,0 +1,42 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/* 文件路径写成"-"时，表示从标准输入读取或者写到标准输出 */
+pub const STDIO: &str = "-";
+
+/* 命令行没有指定文件位置时，在这些环境变量中查找 */
+pub const CONFIG_ENV: &str = "WG_NEKORAY_CONFIG";
+pub const IPS_ENV: &str = "WG_NEKORAY_IPS";
+pub const OUTPUT_ENV: &str = "WG_NEKORAY_OUTPUT";
+pub const SETTINGS_ENV: &str = "WG_NEKORAY_SETTINGS";
+pub const PROFILE_ENV: &str = "WG_NEKORAY_PROFILE";
+
+/* 默认的文件位置(相对于当前目录) */
+pub const DEFAULT_CONFIG_FILE: &str = "wg-config.conf";
+pub const DEFAULT_IPS_FILE: &str = "ip.txt";
+pub const DEFAULT_CANDIDATES_FILE: &str = "candidates.txt";
+
+pub fn is_stdio(path: &str) -> bool {
+    path == STDIO
+}
+
+/* 读取文件的全部内容，路径为"-"时读取标准输入 */
+pub fn read_input(path: &str) -> io::Result<String> {
+    if is_stdio(path) {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        Ok(contents)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/* 设置文件中的相对路径是相对于设置文件所在的目录，"-"和绝对路径保持不变 */
+pub fn relative_to(dir: &Path, path: &str) -> String {
+    if is_stdio(path) || Path::new(path).is_absolute() {
+        path.to_string()
+    } else {
+        dir.join(path).to_string_lossy().to_string()
+    }
+}
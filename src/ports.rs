@@ -0,0 +1,86 @@
+use crate::config::WireGuardConfig;
+use std::str::FromStr;
+
+/* ip.txt中只有主机地址、配置文件中也没有Endpoint时使用的端口 */
+pub const DEFAULT_PORT: u16 = 2408;
+
+/* WARP已知的UDP端口 */
+pub const WARP_PORTS: [u16; 54] = [
+    500, 854, 859, 864, 878, 880, 890, 891, 894, 903, 908, 928, 934, 939, 942, 943, 945, 946, 955,
+    968, 987, 988, 1002, 1010, 1014, 1018, 1070, 1074, 1180, 1387, 1701, 1843, 2371, 2408, 2506,
+    3138, 3476, 3581, 3854, 4177, 4198, 4233, 4500, 5279, 5956, 7103, 7152, 7156, 7281, 7559, 8319,
+    8742, 8854, 8886,
+];
+
+/*
+ip.txt中没有端口的主机地址(比如扫描器输出的IP)使用哪些端口：
+    1、auto(默认)：wg-config.conf中[Peer]的Endpoint端口，没有时使用2408；
+    2、warp：WARP已知的全部UDP端口，每个主机地址生成多个Endpoint；
+    3、端口列表，比如"2408,500,1701"
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BarePorts {
+    #[default]
+    Auto,
+    List(Vec<u16>),
+}
+
+impl BarePorts {
+    /* 实际使用的端口列表 */
+    pub fn resolve(&self, config: &WireGuardConfig) -> Vec<u16> {
+        match self {
+            BarePorts::List(ports) => ports.clone(),
+            BarePorts::Auto => {
+                let mut ports: Vec<u16> = Vec::new();
+                for endpoint in config
+                    .peers
+                    .iter()
+                    .filter_map(|peer| peer.endpoint.as_ref())
+                {
+                    if !ports.contains(&endpoint.port) {
+                        ports.push(endpoint.port);
+                    }
+                }
+                if ports.is_empty() {
+                    ports.push(DEFAULT_PORT);
+                }
+                ports
+            }
+        }
+    }
+}
+
+impl FromStr for BarePorts {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" | "config" => return Ok(BarePorts::Auto),
+            "warp" => return Ok(BarePorts::List(WARP_PORTS.to_vec())),
+            _ => {}
+        }
+        let mut ports: Vec<u16> = Vec::new();
+        for part in s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+        {
+            match part.parse::<u16>() {
+                Ok(port) if port > 0 => {
+                    if !ports.contains(&port) {
+                        ports.push(port);
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "端口列表中的“{}”不合法，可选的值：auto、warp或者逗号分隔的端口(比如2408,500,1701)",
+                        part
+                    ))
+                }
+            }
+        }
+        if ports.is_empty() {
+            return Err("端口列表不能为空".to_string());
+        }
+        Ok(BarePorts::List(ports))
+    }
+}
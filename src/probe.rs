@@ -0,0 +1,401 @@
+use crate::config::WireGuardConfig;
+use crate::endpoint::{Endpoint, EndpointMetrics};
+use crate::error::Error;
+use crate::handshake::{decode_key, respond, Initiation, INITIATION_LEN};
+use crate::node::NodeOptions;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/* 每个Endpoint默认发送几次握手、等待响应多久、同时测试几个Endpoint */
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+pub const DEFAULT_TIMEOUT_MS: u64 = 1000;
+pub const DEFAULT_WORKERS: usize = 16;
+
+/* 测试的参数 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeOptions {
+    pub attempts: u32,
+    pub timeout: Duration,
+    pub workers: usize,
+}
+
+impl Default for ProbeOptions {
+    fn default() -> Self {
+        ProbeOptions {
+            attempts: DEFAULT_ATTEMPTS,
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            workers: DEFAULT_WORKERS,
+        }
+    }
+}
+
+/* 发起握手使用的密钥：配置文件的私钥，第一个[Peer]的公钥、预共享密钥和reserved */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeKeys {
+    pub private_key: [u8; 32],
+    pub peer_public: [u8; 32],
+    pub preshared_key: Option<[u8; 32]>,
+    pub reserved: Option<[u8; 3]>,
+}
+
+impl ProbeKeys {
+    /* 从WireGuard配置读取密钥，密钥不是32字节的base64时返回错误 */
+    pub fn from_config(config: &WireGuardConfig, options: &NodeOptions) -> Result<Self, Error> {
+        let peer = config.peers.first().ok_or(Error::MissingKey("[Peer]"))?;
+        let invalid = |key: &'static str, value: &str| Error::InvalidValue {
+            key,
+            value: value.to_string(),
+        };
+        let private_key = decode_key(&config.interface.private_key)
+            .ok_or_else(|| invalid("PrivateKey", &config.interface.private_key))?;
+        let peer_public =
+            decode_key(&peer.public_key).ok_or_else(|| invalid("PublicKey", &peer.public_key))?;
+        let preshared_key = match &peer.preshared_key {
+            Some(key) => Some(decode_key(key).ok_or_else(|| invalid("PresharedKey", key))?),
+            None => None,
+        };
+        Ok(ProbeKeys {
+            private_key,
+            peer_public,
+            preshared_key,
+            reserved: options
+                .reserved_for(config, peer)
+                .map(|reserved| reserved.0),
+        })
+    }
+}
+
+/* 一个Endpoint的测试结果：发送了几次握手、收到了几次响应，以及每次响应的往返时间 */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeResult {
+    pub endpoint: Endpoint,
+    pub sent: u32,
+    pub received: u32,
+    pub rtts: Vec<Duration>,
+}
+
+impl ProbeResult {
+    /* 平均往返时间(毫秒)，一次响应都没有收到时返回None */
+    pub fn latency(&self) -> Option<f64> {
+        if self.rtts.is_empty() {
+            return None;
+        }
+        let total: Duration = self.rtts.iter().sum();
+        Some(total.as_secs_f64() * 1000.0 / self.rtts.len() as f64)
+    }
+
+    /* 丢包率(百分比) */
+    pub fn loss(&self) -> f64 {
+        if self.sent == 0 {
+            return 100.0;
+        }
+        (self.sent - self.received) as f64 * 100.0 / self.sent as f64
+    }
+
+    /* 转换成跟扫描器result.csv一样的测量值 */
+    pub fn metrics(&self) -> EndpointMetrics {
+        EndpointMetrics {
+            latency: self.latency(),
+            loss: Some(self.loss()),
+        }
+    }
+}
+
+/* 发送一次握手并等待响应，收到正确的响应时返回往返时间 */
+fn handshake_once(socket: &UdpSocket, keys: &ProbeKeys, timeout: Duration) -> Option<Duration> {
+    let initiation = Initiation::new(
+        &keys.private_key,
+        &keys.peer_public,
+        keys.preshared_key,
+        keys.reserved,
+        rand::random(),
+    );
+    let started = Instant::now();
+    socket.send(&initiation.packet).ok()?;
+    let mut buffer = [0u8; 256];
+    // 收到的不是这次握手的响应时(比如上一次超时后才到的响应)，继续等待到超时
+    loop {
+        // set_read_timeout不接受0，剩余时间为0时同样算作超时
+        let remaining = timeout
+            .checked_sub(started.elapsed())
+            .filter(|remaining| !remaining.is_zero())?;
+        socket.set_read_timeout(Some(remaining)).ok()?;
+        let size = socket.recv(&mut buffer).ok()?;
+        if initiation.verify_response(&buffer[..size]) {
+            return Some(started.elapsed());
+        }
+    }
+}
+
+/* 测试一个Endpoint：发送attempts次握手，域名先解析成IP地址(解析失败时全部算作丢包) */
+pub fn probe_endpoint(
+    endpoint: &Endpoint,
+    keys: &ProbeKeys,
+    options: &ProbeOptions,
+) -> ProbeResult {
+    let mut result = ProbeResult {
+        endpoint: endpoint.clone(),
+        sent: options.attempts,
+        received: 0,
+        rtts: Vec::new(),
+    };
+    let address = match (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+    {
+        Some(address) => address,
+        None => return result,
+    };
+    let local: SocketAddr = if address.is_ipv4() {
+        "0.0.0.0:0".parse().expect("合法的地址")
+    } else {
+        "[::]:0".parse().expect("合法的地址")
+    };
+    let socket = match UdpSocket::bind(local).and_then(|socket| {
+        socket.connect(address)?;
+        Ok(socket)
+    }) {
+        Ok(socket) => socket,
+        Err(_) => return result,
+    };
+    for _ in 0..options.attempts {
+        if let Some(rtt) = handshake_once(&socket, keys, options.timeout) {
+            result.received += 1;
+            result.rtts.push(rtt);
+        }
+    }
+    result
+}
+
+/* 同时使用options.workers个线程测试所有Endpoint，每测试完一个调用一次progress，结果按照原来的顺序返回 */
+pub fn probe_endpoints<F>(
+    endpoints: &[Endpoint],
+    keys: &ProbeKeys,
+    options: &ProbeOptions,
+    progress: F,
+) -> Vec<ProbeResult>
+where
+    F: Fn(&ProbeResult) + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<ProbeResult>>> = Mutex::new(vec![None; endpoints.len()]);
+    thread::scope(|scope| {
+        for _ in 0..options.workers.clamp(1, endpoints.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(endpoint) = endpoints.get(index) else {
+                    break;
+                };
+                let result = probe_endpoint(endpoint, keys, options);
+                progress(&result);
+                results.lock().expect("测试结果的锁已损坏")[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .expect("测试结果的锁已损坏")
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/* 按照丢包率从低到高、平均往返时间从短到长排列，没有收到响应的排在最后 */
+pub fn rank_results(results: &mut [ProbeResult]) {
+    results.sort_by(|a, b| {
+        a.loss().total_cmp(&b.loss()).then_with(|| {
+            let latency = |result: &ProbeResult| result.latency().unwrap_or(f64::INFINITY);
+            latency(a).total_cmp(&latency(b))
+        })
+    });
+}
+
+/*
+本地的握手响应方，代替WARP的服务器测试探测功能：
+    在指定的地址(比如127.0.0.1:0)监听UDP，用private_key验证收到的握手初始消息并回复握手响应；
+    drop时停止监听线程
+*/
+pub struct LoopbackResponder {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl LoopbackResponder {
+    pub fn bind<A: ToSocketAddrs>(
+        address: A,
+        private_key: [u8; 32],
+        preshared_key: Option<[u8; 32]>,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        let address = socket.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let mut buffer = [0u8; INITIATION_LEN * 2];
+            while !stopped.load(Ordering::Relaxed) {
+                let Ok((size, peer)) = socket.recv_from(&mut buffer) else {
+                    continue;
+                };
+                if let Some(response) =
+                    respond(&private_key, preshared_key, &buffer[..size], rand::random())
+                {
+                    let _ = socket.send_to(&response, peer);
+                }
+            }
+        });
+        Ok(LoopbackResponder {
+            address,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /* 实际监听的地址 */
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /* 监听地址对应的Endpoint */
+    pub fn endpoint(&self) -> Endpoint {
+        Endpoint {
+            host: self.address.ip().to_string(),
+            port: self.address.port(),
+        }
+    }
+}
+
+impl Drop for LoopbackResponder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::EndpointList;
+    use crate::handshake::public_key_of;
+
+    const RESPONDER_KEY: [u8; 32] = [33; 32];
+
+    fn keys(peer_public: [u8; 32], preshared_key: Option<[u8; 32]>) -> ProbeKeys {
+        ProbeKeys {
+            private_key: [1; 32],
+            peer_public,
+            preshared_key,
+            reserved: None,
+        }
+    }
+
+    fn probe(responder: &LoopbackResponder, keys: &ProbeKeys) -> ProbeResult {
+        let options = ProbeOptions {
+            attempts: 3,
+            timeout: Duration::from_millis(500),
+            workers: 2,
+        };
+        let mut results = probe_endpoints(&[responder.endpoint()], keys, &options, |_| {});
+        assert_eq!(results.len(), 1);
+        results.remove(0)
+    }
+
+    #[test]
+    fn loopback_responder_answers_every_attempt() {
+        let responder = LoopbackResponder::bind("127.0.0.1:0", RESPONDER_KEY, None).unwrap();
+        let result = probe(&responder, &keys(public_key_of(&RESPONDER_KEY), None));
+        assert_eq!(result.received, 3);
+        assert_eq!(result.rtts.len(), 3);
+        assert_eq!(result.loss(), 0.0);
+        assert!(result.latency().is_some());
+    }
+
+    #[test]
+    fn loopback_responder_with_preshared_key_and_reserved() {
+        let preshared_key = Some([5; 32]);
+        let responder =
+            LoopbackResponder::bind("127.0.0.1:0", RESPONDER_KEY, preshared_key).unwrap();
+        let keys = ProbeKeys {
+            reserved: Some([1, 2, 3]),
+            ..keys(public_key_of(&RESPONDER_KEY), preshared_key)
+        };
+        assert_eq!(probe(&responder, &keys).received, 3);
+
+        // 预共享密钥不一致时响应无法验证
+        let result = probe(
+            &responder,
+            &ProbeKeys {
+                preshared_key: None,
+                ..keys
+            },
+        );
+        assert_eq!(result.received, 0);
+    }
+
+    #[test]
+    fn wrong_peer_key_gets_no_response() {
+        let responder = LoopbackResponder::bind("127.0.0.1:0", RESPONDER_KEY, None).unwrap();
+        let result = probe(&responder, &keys(public_key_of(&[2; 32]), None));
+        assert_eq!(result.received, 0);
+        assert_eq!(result.loss(), 100.0);
+        assert_eq!(result.latency(), None);
+    }
+
+    #[test]
+    fn unreachable_endpoints_exceed_the_latency_cap() {
+        let responder = LoopbackResponder::bind("127.0.0.1:0", RESPONDER_KEY, None).unwrap();
+        let silent = LoopbackResponder::bind("127.0.0.1:0", [2; 32], None).unwrap();
+        let options = ProbeOptions {
+            attempts: 1,
+            timeout: Duration::from_millis(200),
+            workers: 2,
+        };
+        let endpoints = [silent.endpoint(), responder.endpoint()];
+        let results = probe_endpoints(
+            &endpoints,
+            &keys(public_key_of(&RESPONDER_KEY), None),
+            &options,
+            |_| {},
+        );
+        let mut list = EndpointList {
+            endpoints: endpoints.to_vec(),
+            metrics: results
+                .iter()
+                .map(|result| (result.endpoint.clone(), result.metrics()))
+                .collect(),
+            ..EndpointList::default()
+        };
+        assert_eq!(list.filter_by_metrics(Some(150.0), None), 1);
+        assert_eq!(list.endpoints, [responder.endpoint()]);
+    }
+
+    #[test]
+    fn results_are_ranked_by_loss_then_latency() {
+        let result = |host: &str, received: u32, rtt: u64| ProbeResult {
+            endpoint: Endpoint {
+                host: host.to_string(),
+                port: 2408,
+            },
+            sent: 2,
+            received,
+            rtts: vec![Duration::from_millis(rtt); received as usize],
+        };
+        let mut results = vec![
+            result("1.1.1.1", 0, 0),
+            result("1.1.1.2", 1, 10),
+            result("1.1.1.3", 2, 80),
+            result("1.1.1.4", 2, 20),
+        ];
+        rank_results(&mut results);
+        let hosts: Vec<&str> = results.iter().map(|r| r.endpoint.host.as_str()).collect();
+        assert_eq!(hosts, ["1.1.1.4", "1.1.1.3", "1.1.1.2", "1.1.1.1"]);
+    }
+}
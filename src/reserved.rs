@@ -0,0 +1,46 @@
+use std::fmt;
+use std::str::FromStr;
+
+/* WARP的reserved字段：握手包头部保留的3个字节，很多网络环境下必须填写正确才能握手成功 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Reserved(pub [u8; 3]);
+
+impl Reserved {
+    /* 从base64编码的client_id（比如"HVlB"）解码出3个字节 */
+    pub fn from_client_id(client_id: &str) -> Option<Self> {
+        let bytes = base64::decode(client_id.trim()).ok()?;
+        match bytes.as_slice() {
+            [a, b, c] => Some(Reserved([*a, *b, *c])),
+            _ => None,
+        }
+    }
+}
+
+/* 支持"1,2,3"、"[1, 2, 3]"、"1 2 3"这几种写法，以及base64编码的client_id */
+impl FromStr for Reserved {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().trim_start_matches('[').trim_end_matches(']');
+        let numbers: Vec<&str> = trimmed
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .collect();
+        if numbers.len() == 3 && numbers.iter().all(|n| n.parse::<u8>().is_ok()) {
+            let bytes: Vec<u8> = numbers.iter().map(|n| n.parse::<u8>().unwrap()).collect();
+            return Ok(Reserved([bytes[0], bytes[1], bytes[2]]));
+        }
+        Reserved::from_client_id(s).ok_or_else(|| {
+            format!(
+                "reserved的值“{}”不合法，应该是3个0~255的数字(比如1,2,3)或者base64编码的client_id",
+                s.trim()
+            )
+        })
+    }
+}
+
+impl fmt::Display for Reserved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.0[0], self.0[1], self.0[2])
+    }
+}
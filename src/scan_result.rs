@@ -0,0 +1,291 @@
+use crate::endpoint::{collect_lines, expand_line, EndpointList, EndpointMetrics};
+use crate::ports::DEFAULT_PORT;
+use std::net::Ipv6Addr;
+
+/* 表头中各列名称包含的关键字 */
+const ENDPOINT_KEYWORDS: [&str; 3] = ["ip", "endpoint", "地址"];
+const PORT_KEYWORDS: [&str; 2] = ["端口", "port"];
+const LOSS_KEYWORDS: [&str; 2] = ["丢包", "loss"];
+const LATENCY_KEYWORDS: [&str; 4] = ["延迟", "latency", "delay", "ping"];
+
+/* 单元格中是否有某个关键字(不区分大小写) */
+fn has_keyword(cell: &str, keywords: &[&str]) -> bool {
+    let cell = cell.to_ascii_lowercase();
+    keywords.iter().any(|keyword| cell.contains(keyword))
+}
+
+/* result.csv中各列的位置：Endpoint(或IP)列、单独的端口列、丢包率列、延迟列 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Columns {
+    endpoint: usize,
+    port: Option<usize>,
+    loss: Option<usize>,
+    latency: Option<usize>,
+}
+
+impl Columns {
+    /*
+    根据表头找出各列的位置，比如：
+        CloudflareST："IP 地址,已发送,已接收,丢包率,平均延迟,下载速度 (MB/s)"
+        warp-yxip："IP:端口,丢包率,延迟"
+    */
+    fn from_header(cells: &[&str]) -> Self {
+        let find = |keywords: &[&str]| cells.iter().position(|cell| has_keyword(cell, keywords));
+        let endpoint = find(&ENDPOINT_KEYWORDS).unwrap_or(0);
+        let port = find(&PORT_KEYWORDS).filter(|port| *port != endpoint);
+        Columns {
+            endpoint,
+            port,
+            loss: find(&LOSS_KEYWORDS),
+            latency: find(&LATENCY_KEYWORDS),
+        }
+    }
+
+    /* 第一行的第一列不是Endpoint(或者只有IP地址)，并且有列名的关键字时才是表头，否则当作数据行 */
+    fn is_header(cells: &[&str]) -> bool {
+        expand_line(cells[0], &[DEFAULT_PORT]).is_err()
+            && cells.iter().any(|cell| {
+                [
+                    &ENDPOINT_KEYWORDS[..],
+                    &PORT_KEYWORDS[..],
+                    &LOSS_KEYWORDS[..],
+                    &LATENCY_KEYWORDS[..],
+                ]
+                .iter()
+                .any(|keywords| has_keyword(cell, keywords))
+            })
+    }
+
+    /* 没有表头时按照列数猜测：5列以上的是CloudflareST的格式，其他的是warp-yxip的格式 */
+    fn guess(cells: &[&str]) -> Self {
+        if cells.len() >= 5 {
+            Columns {
+                endpoint: 0,
+                port: None,
+                loss: Some(3),
+                latency: Some(4),
+            }
+        } else {
+            Columns {
+                endpoint: 0,
+                port: None,
+                loss: Some(1),
+                latency: Some(2),
+            }
+        }
+    }
+}
+
+/* 拆分CSV的一行，去掉每个单元格首尾的空白和引号 */
+fn split_row(row: &str) -> Vec<&str> {
+    row.split(',')
+        .map(|cell| cell.trim().trim_matches('"').trim())
+        .collect()
+}
+
+/* 丢包率转换成百分比："0.00%"、"12.5%"是百分比；没有"%"且不大于1的是比例(CloudflareST的写法) */
+fn parse_loss(cell: &str) -> Option<f64> {
+    match cell.strip_suffix('%') {
+        Some(percent) => percent.trim().parse().ok(),
+        None => {
+            let value: f64 = cell.parse().ok()?;
+            Some(if value <= 1.0 { value * 100.0 } else { value })
+        }
+    }
+}
+
+/* 延迟转换成毫秒："95"、"95.12"、"95 ms" */
+fn parse_latency(cell: &str) -> Option<f64> {
+    let lower = cell.to_ascii_lowercase();
+    lower.trim_end_matches("ms").trim().parse().ok()
+}
+
+/* 去掉行中"#"后面的注释和首尾的空白 */
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or_default().trim()
+}
+
+/* 判断ip.txt是不是扫描器输出的CSV文件：第一个不是注释的行(去掉行尾的注释后)中有逗号 */
+pub fn is_scan_result(contents: &str) -> bool {
+    contents
+        .lines()
+        .map(strip_comment)
+        .find(|line| !line.is_empty())
+        .is_some_and(|line| line.contains(','))
+}
+
+/*
+解析CloudflareST、warp-yxip等扫描器输出的result.csv：
+    有表头时根据表头找到Endpoint列、端口列、丢包率列和延迟列，没有表头时按照列数猜测；
+    Endpoint列只有IP地址时跟bare_ports中的每个端口组合，丢包率和延迟作为Endpoint的测量值。
+*/
+pub fn parse_scan_result(contents: &str, bare_ports: &[u16]) -> EndpointList {
+    let mut columns: Option<Columns> = None;
+    let mut rows = Vec::new();
+    for (idx, raw_line) in contents.lines().enumerate() {
+        let line = strip_comment(raw_line);
+        if line.is_empty() {
+            continue;
+        }
+        let cells = split_row(line);
+        let layout = match columns {
+            Some(layout) => layout,
+            None => {
+                let is_header = Columns::is_header(&cells);
+                let layout = if is_header {
+                    Columns::from_header(&cells)
+                } else {
+                    Columns::guess(&cells)
+                };
+                columns = Some(layout);
+                if is_header {
+                    continue;
+                }
+                layout
+            }
+        };
+        let cell = |index: Option<usize>| index.and_then(|index| cells.get(index).copied());
+        let target = match cell(layout.port) {
+            Some(port) if !port.is_empty() => format!(
+                "{} {}",
+                cell(Some(layout.endpoint)).unwrap_or_default(),
+                port
+            ),
+            // 只有IPv6地址的单元格加上中括号，否则会被当作格式不明确
+            _ => match cell(Some(layout.endpoint)).unwrap_or_default() {
+                ip if ip.parse::<Ipv6Addr>().is_ok() => format!("[{}]", ip),
+                target => target.to_string(),
+            },
+        };
+        let metrics = EndpointMetrics {
+            latency: cell(layout.latency).and_then(parse_latency),
+            loss: cell(layout.loss).and_then(parse_loss),
+        };
+        rows.push((
+            idx + 1,
+            line.to_string(),
+            expand_line(&target, bare_ports),
+            metrics,
+        ));
+    }
+    collect_lines(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::{parse_endpoint_lines, Endpoint};
+
+    const CLOUDFLARE_ST_ROWS: &str = "\
+162.159.192.1,4,4,0.00,95.12,0.00
+2606:4700:d0::a29f:c001,4,2,0.50,120.00,0.00
+";
+
+    const WARP_YXIP_ROWS: &str = "\
+162.159.192.1:2408,0.00%,95 ms
+[2606:4700:d0::a29f:c001]:864,100.00%,
+";
+
+    fn endpoint(host: &str, port: u16) -> Endpoint {
+        Endpoint {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    fn metrics(list: &EndpointList, endpoint: &Endpoint) -> EndpointMetrics {
+        list.metrics.get(endpoint).copied().unwrap_or_default()
+    }
+
+    /* CloudflareST只有IP地址，端口来自bare_ports */
+    fn check_cloudflare_st(list: &EndpointList) {
+        assert!(list.rejected.is_empty(), "{:?}", list.rejected);
+        let v4 = endpoint("162.159.192.1", 2408);
+        let v6 = endpoint("2606:4700:d0::a29f:c001", 2408);
+        assert_eq!(list.endpoints, [v4.clone(), v6.clone()]);
+        assert_eq!(metrics(list, &v4).latency, Some(95.12));
+        assert_eq!(metrics(list, &v4).loss, Some(0.0));
+        assert_eq!(metrics(list, &v6).loss, Some(50.0));
+    }
+
+    fn check_warp_yxip(list: &EndpointList) {
+        assert!(list.rejected.is_empty(), "{:?}", list.rejected);
+        let v4 = endpoint("162.159.192.1", 2408);
+        let v6 = endpoint("2606:4700:d0::a29f:c001", 864);
+        assert_eq!(list.endpoints, [v4.clone(), v6.clone()]);
+        assert_eq!(metrics(list, &v4).latency, Some(95.0));
+        // 一次响应都没有收到时延迟是空的
+        assert_eq!(metrics(list, &v6).latency, None);
+        assert_eq!(metrics(list, &v6).loss, Some(100.0));
+    }
+
+    #[test]
+    fn cloudflare_st_with_header() {
+        let contents = format!(
+            "IP 地址,已发送,已接收,丢包率,平均延迟,下载速度 (MB/s)\n{}",
+            CLOUDFLARE_ST_ROWS
+        );
+        assert!(is_scan_result(&contents));
+        check_cloudflare_st(&parse_endpoint_lines(&contents, &[2408]));
+    }
+
+    #[test]
+    fn cloudflare_st_without_header() {
+        assert!(is_scan_result(CLOUDFLARE_ST_ROWS));
+        check_cloudflare_st(&parse_endpoint_lines(CLOUDFLARE_ST_ROWS, &[2408]));
+    }
+
+    #[test]
+    fn warp_yxip_with_header() {
+        let contents = format!("IP:端口,丢包率,延迟\n{}", WARP_YXIP_ROWS);
+        check_warp_yxip(&parse_endpoint_lines(&contents, &[]));
+    }
+
+    #[test]
+    fn warp_yxip_without_header() {
+        check_warp_yxip(&parse_endpoint_lines(WARP_YXIP_ROWS, &[]));
+    }
+
+    #[test]
+    fn separate_port_column() {
+        let contents = "\"ip\",\"port\",\"loss\",\"latency\"\n\"2606:4700:d0::a29f:c001\",\"864\",\"0\",\"80\"\n";
+        let list = parse_scan_result(contents, &[]);
+        assert_eq!(list.endpoints, [endpoint("2606:4700:d0::a29f:c001", 864)]);
+        assert_eq!(metrics(&list, &list.endpoints[0]).latency, Some(80.0));
+    }
+
+    #[test]
+    fn trailing_comments_are_ignored() {
+        // 行尾注释中的逗号不算
+        assert!(!is_scan_result(
+            "# 优选IP, 来自扫描器\n162.159.192.1:2408 # 最快, 95ms\n"
+        ));
+        assert!(is_scan_result(
+            "# 扫描结果\n\n162.159.192.1:2408,0.00%,95 # 最快\n"
+        ));
+        let list = parse_endpoint_lines("# 扫描结果\n162.159.192.1:2408,0.00%,95 # 最快\n", &[]);
+        assert_eq!(list.endpoints, [endpoint("162.159.192.1", 2408)]);
+    }
+
+    #[test]
+    fn invalid_and_duplicate_rows_keep_their_line_numbers() {
+        let contents = "IP:端口,丢包率,延迟\n162.159.192.1:2408,0.00%,95\nnot_a_host:2408,0.00%,95\n162.159.192.1:2408,0.00%,99\n";
+        let list = parse_scan_result(contents, &[]);
+        assert_eq!(list.endpoints.len(), 1);
+        assert_eq!(list.rejected[0].line, 3);
+        assert_eq!(list.rejected[1].line, 4);
+        // 重复的行不会覆盖第一次出现时的测量值
+        assert_eq!(metrics(&list, &list.endpoints[0]).latency, Some(95.0));
+    }
+
+    #[test]
+    fn loss_and_latency_formats() {
+        assert_eq!(parse_loss("12.5%"), Some(12.5));
+        assert_eq!(parse_loss("0.25"), Some(25.0));
+        assert_eq!(parse_loss("30"), Some(30.0));
+        assert_eq!(parse_loss(""), None);
+        assert_eq!(parse_latency("95.12"), Some(95.12));
+        assert_eq!(parse_latency("95 MS"), Some(95.0));
+        assert_eq!(parse_latency("timeout"), None);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::error::Error;
+use crate::format::OutputFormat;
+use crate::naming::NameTemplate;
+use crate::paths::relative_to;
+use crate::ports::BarePorts;
+use crate::reserved::Reserved;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/* 当前目录中的设置文件名 */
+pub const SETTINGS_FILE: &str = "wg-nekoray.toml";
+/* 用户配置目录(比如~/.config、%APPDATA%)中的设置文件 */
+pub const USER_SETTINGS_DIR: &str = "wg-nekoray";
+pub const USER_SETTINGS_FILE: &str = "settings.toml";
+
+/*
+TOML格式的设置文件，保存常用的默认值，命令行参数和环境变量优先于设置文件：
+    config = "wg-config.conf"
+    prefix = "CN"
+    name-template = "{prefix}_{host}:{port}"
+    mtu = 1280
+    format = "native"
+    [profiles.hk]
+    prefix = "HK"
+    ips = "hk.txt"
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    pub config: Option<String>,
+    pub ips: Option<String>,
+    pub output: Option<String>,
+    pub mtu: Option<u16>,
+    pub prefix: Option<String>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub name_template: Option<NameTemplate>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub format: Option<OutputFormat>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub reserved: Option<Reserved>,
+    pub nekoray_port: Option<u16>,
+    pub socks_port: Option<u16>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub ports: Option<BarePorts>,
+    // 使用--profile 名称 选择的配置，覆盖上面的默认值
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+impl Settings {
+    /* 读取指定的设置文件，文件中的相对路径转换成相对于设置文件所在目录的路径 */
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        let file = file.as_ref();
+        let invalid = |message: String| Error::Settings {
+            file: file.display().to_string(),
+            message,
+        };
+        let contents = fs::read_to_string(file)?;
+        let mut settings: Settings =
+            toml::from_str(&contents).map_err(|err| invalid(err.message().to_string()))?;
+        if let Some(message) = settings.validate() {
+            return Err(invalid(message));
+        }
+        if let Some(dir) = file.parent() {
+            settings.resolve_paths(dir);
+        }
+        Ok(settings)
+    }
+
+    /*
+    读取设置文件并合并，后面的优先：
+        1、用户配置目录中的wg-nekoray/settings.toml；
+        2、命令行或环境变量指定的设置文件，没有指定时为当前目录的wg-nekoray.toml；
+        3、指定了profile时，再使用[profiles.名称]中的值
+    */
+    pub fn load(file: Option<&str>, profile: Option<&str>) -> Result<Self, Error> {
+        let mut settings = match user_settings_file() {
+            Some(user_file) if user_file.is_file() => Self::from_file(user_file)?,
+            _ => Settings::default(),
+        };
+        let local = match file {
+            Some(file) => Some(Self::from_file(file)?),
+            None if Path::new(SETTINGS_FILE).is_file() => Some(Self::from_file(SETTINGS_FILE)?),
+            None => None,
+        };
+        if let Some(local) = local {
+            settings = settings.merge(local);
+        }
+        match profile {
+            Some(name) => {
+                let selected = settings
+                    .profiles
+                    .remove(name)
+                    .ok_or_else(|| Error::Settings {
+                        file: file.unwrap_or(SETTINGS_FILE).to_string(),
+                        message: format!("没有名为“{}”的profile", name),
+                    })?;
+                Ok(settings.merge(selected))
+            }
+            None => Ok(settings),
+        }
+    }
+
+    /* 合并两份设置，other中有的值覆盖self中的值；同名的profile也逐项合并 */
+    pub fn merge(self, other: Settings) -> Settings {
+        let mut profiles = self.profiles;
+        for (name, profile) in other.profiles {
+            let merged = match profiles.remove(&name) {
+                Some(existing) => existing.merge(profile),
+                None => profile,
+            };
+            profiles.insert(name, merged);
+        }
+        Settings {
+            config: other.config.or(self.config),
+            ips: other.ips.or(self.ips),
+            output: other.output.or(self.output),
+            mtu: other.mtu.or(self.mtu),
+            prefix: other.prefix.or(self.prefix),
+            name_template: other.name_template.or(self.name_template),
+            format: other.format.or(self.format),
+            reserved: other.reserved.or(self.reserved),
+            nekoray_port: other.nekoray_port.or(self.nekoray_port),
+            socks_port: other.socks_port.or(self.socks_port),
+            ports: other.ports.or(self.ports),
+            profiles,
+        }
+    }
+
+    /* 检查取值范围，不合法时返回错误信息 */
+    fn validate(&self) -> Option<String> {
+        if let Some(mtu) = self.mtu {
+            if !(1280..=1500).contains(&mtu) {
+                return Some(format!("mtu的值{}不在1280~1500之间", mtu));
+            }
+        }
+        self.profiles.iter().find_map(|(name, profile)| {
+            if !profile.profiles.is_empty() {
+                return Some(format!("profile“{}”中不能再包含profiles", name));
+            }
+            profile
+                .validate()
+                .map(|message| format!("profile“{}”：{}", name, message))
+        })
+    }
+
+    /* 把文件位置转换成相对于dir的路径 */
+    fn resolve_paths(&mut self, dir: &Path) {
+        for path in [&mut self.config, &mut self.ips, &mut self.output]
+            .into_iter()
+            .flatten()
+        {
+            *path = relative_to(dir, path);
+        }
+        for profile in self.profiles.values_mut() {
+            profile.resolve_paths(dir);
+        }
+    }
+}
+
+/* 用户配置目录中的设置文件位置，找不到用户配置目录时返回None */
+pub fn user_settings_file() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(USER_SETTINGS_DIR).join(USER_SETTINGS_FILE))
+}
+
+/* 设置文件中的字符串使用FromStr解析，跟命令行参数的写法一样 */
+fn from_str_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
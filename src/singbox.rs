@@ -0,0 +1,265 @@
+use crate::config::{Interface, Peer, WireGuardConfig};
+use crate::endpoint::{is_valid_host, Endpoint};
+use crate::error::Error;
+use crate::naming::NodeNaming;
+use crate::node::{plan_nodes, NodeOptions};
+use crate::reserved::Reserved;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+use std::str::FromStr;
+
+/* 导出的sing-box配置使用哪种写法：1.11之前的wireguard出站(outbounds)，或者1.11开始的wireguard端点(endpoints) */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SingBoxTarget {
+    #[default]
+    Outbound,
+    Endpoint,
+}
+
+/* 支持"outbound"、"endpoint"，也可以直接写sing-box的版本号(比如1.10、1.11.4) */
+impl FromStr for SingBoxTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "legacy" | "outbound" | "outbounds" => return Ok(SingBoxTarget::Outbound),
+            "endpoint" | "endpoints" => return Ok(SingBoxTarget::Endpoint),
+            _ => {}
+        }
+        let version: Vec<u32> = value
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| {
+                format!(
+                    "不支持的sing-box版本“{}”，可选的值：outbound、endpoint或者版本号(比如1.11)",
+                    s.trim()
+                )
+            })?;
+        match version.as_slice() {
+            [major, minor, ..] if (*major, *minor) >= (1, 11) => Ok(SingBoxTarget::Endpoint),
+            [major, ..] if *major > 1 => Ok(SingBoxTarget::Endpoint),
+            _ => Ok(SingBoxTarget::Outbound),
+        }
+    }
+}
+
+/* sing-box的wireguard出站(outbound)，字段按字母顺序排列，跟NekoBox导出的格式一致 */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireGuardOutbound {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface_name: Option<String>,
+    // 只有一个地址时，NekoBox会写成字符串而不是数组，两种写法都要能读取
+    #[serde(deserialize_with = "string_or_list")]
+    pub local_address: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u16>,
+    pub peer_public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_keepalive_interval: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_shared_key: Option<String>,
+    pub private_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved: Option<[u8; 3]>,
+    pub server: String,
+    pub server_port: u16,
+    #[serde(default)]
+    pub system_interface: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl WireGuardOutbound {
+    /* 以WireGuard配置文件的参数为基准，使用指定的[Peer]和Endpoint生成出站 */
+    pub fn new(
+        config: &WireGuardConfig,
+        peer: &Peer,
+        endpoint: &Endpoint,
+        tag: &str,
+        options: &NodeOptions,
+    ) -> Self {
+        WireGuardOutbound {
+            interface_name: Some("WARP".to_string()),
+            local_address: config.interface.addresses.clone(),
+            mtu: Some(options.mtu_for(config)),
+            peer_public_key: peer.public_key.clone(),
+            persistent_keepalive_interval: peer.persistent_keepalive,
+            pre_shared_key: peer.preshared_key.clone(),
+            private_key: config.interface.private_key.clone(),
+            reserved: options
+                .reserved_for(config, peer)
+                .map(|reserved| reserved.0),
+            server: endpoint.host.clone(),
+            server_port: endpoint.port,
+            system_interface: false,
+            tag: Some(tag.to_string()),
+            kind: "wireguard".to_string(),
+        }
+    }
+}
+
+impl WireGuardOutbound {
+    /* 把出站还原成WireGuard配置文件，[Peer]的Endpoint就是出站的server和server_port */
+    pub fn to_config(&self) -> Result<(WireGuardConfig, Endpoint), Error> {
+        if !is_valid_host(&self.server) {
+            return Err(Error::InvalidValue {
+                key: "server",
+                value: self.server.clone(),
+            });
+        }
+        let endpoint = Endpoint {
+            host: self.server.clone(),
+            port: self.server_port,
+        };
+        let config = WireGuardConfig {
+            interface: Interface {
+                private_key: self.private_key.clone(),
+                addresses: self.local_address.clone(),
+                mtu: self.mtu,
+                ..Interface::default()
+            },
+            peers: vec![Peer {
+                public_key: self.peer_public_key.clone(),
+                preshared_key: self.pre_shared_key.clone(),
+                allowed_ips: vec!["0.0.0.0/0".to_string(), "::/0".to_string()],
+                endpoint: Some(endpoint.clone()),
+                persistent_keepalive: self.persistent_keepalive_interval,
+                reserved: self.reserved.map(Reserved),
+                ..Peer::default()
+            }],
+            sections: Vec::new(),
+        };
+        Ok((config, endpoint))
+    }
+}
+
+/* sing-box 1.11开始的wireguard端点(endpoint) */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireGuardEndpoint {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub tag: String,
+    pub system: bool,
+    pub mtu: u16,
+    pub address: Vec<String>,
+    pub private_key: String,
+    pub peers: Vec<EndpointPeer>,
+}
+
+/* wireguard端点中的peer */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointPeer {
+    pub address: String,
+    pub port: u16,
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_shared_key: Option<String>,
+    pub allowed_ips: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_keepalive_interval: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved: Option<[u8; 3]>,
+}
+
+impl WireGuardEndpoint {
+    /* 以WireGuard配置文件的参数为基准，使用指定的[Peer]和Endpoint生成端点 */
+    pub fn new(
+        config: &WireGuardConfig,
+        peer: &Peer,
+        endpoint: &Endpoint,
+        tag: &str,
+        options: &NodeOptions,
+    ) -> Self {
+        let allowed_ips = if peer.allowed_ips.is_empty() {
+            vec!["0.0.0.0/0".to_string(), "::/0".to_string()]
+        } else {
+            peer.allowed_ips.clone()
+        };
+        WireGuardEndpoint {
+            kind: "wireguard".to_string(),
+            tag: tag.to_string(),
+            system: false,
+            mtu: options.mtu_for(config),
+            address: config.interface.addresses.clone(),
+            private_key: config.interface.private_key.clone(),
+            peers: vec![EndpointPeer {
+                address: endpoint.host.clone(),
+                port: endpoint.port,
+                public_key: peer.public_key.clone(),
+                pre_shared_key: peer.preshared_key.clone(),
+                allowed_ips,
+                persistent_keepalive_interval: peer.persistent_keepalive,
+                reserved: options
+                    .reserved_for(config, peer)
+                    .map(|reserved| reserved.0),
+            }],
+        }
+    }
+}
+
+/* 批量将ip.txt中的Endpoint生成sing-box配置片段，每个节点一个wireguard出站或端点，tag就是节点名称；每个节点调用一次progress(节点名称, 生成的内容) */
+pub fn generate_singbox_config<F>(
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    cross: bool,
+    target: SingBoxTarget,
+    mut progress: F,
+) -> Option<String>
+where
+    F: FnMut(&str, &str),
+{
+    let specs = plan_nodes(config, endpoints, cross, naming, options);
+    if specs.is_empty() {
+        return None;
+    }
+    let fragment = match target {
+        SingBoxTarget::Outbound => {
+            let outbounds: Vec<WireGuardOutbound> = specs
+                .iter()
+                .map(|spec| WireGuardOutbound {
+                    // 独立的sing-box配置中有多个出站，不能共用同一个网卡名称
+                    interface_name: None,
+                    ..WireGuardOutbound::new(config, spec.peer, &spec.endpoint, &spec.name, options)
+                })
+                .collect();
+            json!({ "outbounds": outbounds })
+        }
+        SingBoxTarget::Endpoint => {
+            let endpoints: Vec<WireGuardEndpoint> = specs
+                .iter()
+                .map(|spec| {
+                    WireGuardEndpoint::new(config, spec.peer, &spec.endpoint, &spec.name, options)
+                })
+                .collect();
+            json!({ "endpoints": endpoints })
+        }
+    };
+    for spec in &specs {
+        progress(&spec.name, "sing-box出站");
+    }
+    Some(serde_json::to_string_pretty(&fragment).expect("无法序列化sing-box配置"))
+}
+
+/* 反序列化时，字符串当作只有一个元素的数组 */
+fn string_or_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::One(value) => vec![value],
+        StringOrList::Many(values) => values,
+    })
+}
@@ -0,0 +1,104 @@
+use crate::config::{Peer, WireGuardConfig};
+use crate::endpoint::Endpoint;
+use crate::naming::NodeNaming;
+use crate::node::{plan_nodes, NodeOptions};
+
+/* 节点名称前缀为空(或者没有ASCII字母、数字)时，wg-quick配置文件使用的文件名前缀 */
+pub const DEFAULT_INTERFACE_PREFIX: &str = "wg";
+
+/* Linux网卡名称的最大长度 */
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+
+/*
+wg-quick使用文件名作为网卡名称，只能使用ASCII字母、数字和"_=+.-"，最长15个字符：
+    前缀中的其他字符换成"_"，太长时截短，保证加上序号后不超过15个字符
+*/
+fn interface_name(prefix: &str, index: usize) -> String {
+    let suffix = index.to_string();
+    let mut name: String = prefix
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_=+.-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !name.chars().any(|c| c.is_ascii_alphanumeric()) {
+        name = DEFAULT_INTERFACE_PREFIX.to_string();
+    }
+    name.truncate(MAX_INTERFACE_NAME_LEN.saturating_sub(suffix.len()));
+    name + &suffix
+}
+
+/* 文件名是不是用这个前缀生成的wg-quick配置文件(比如CN_3.conf) */
+pub fn is_wg_quick_file(prefix: &str, file_name: &str) -> bool {
+    let Some(stem) = file_name.strip_suffix(".conf") else {
+        return false;
+    };
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    match stem[stem.len() - digits..].parse::<usize>() {
+        Ok(index) => interface_name(prefix, index) == stem,
+        Err(_) => false,
+    }
+}
+
+/*
+以wg-config.conf为模板，生成只连接一个Endpoint的WireGuard配置：
+    只保留指定的[Peer]并改写它的Endpoint；用户指定了MTU或reserved时覆盖配置文件中的值，其他参数原样保留；
+    写入文件时使用to_wg_quick()，reserved和wg-quick不认识的参数写成注释
+*/
+pub fn render_wg_quick(
+    config: &WireGuardConfig,
+    peer: &Peer,
+    endpoint: &Endpoint,
+    options: &NodeOptions,
+) -> WireGuardConfig {
+    let mut interface = config.interface.clone();
+    if options.mtu.is_some() {
+        interface.mtu = options.mtu;
+    }
+    let mut peer = peer.clone();
+    peer.endpoint = Some(endpoint.clone());
+    if options.reserved.is_some() {
+        peer.reserved = options.reserved;
+    }
+    WireGuardConfig {
+        interface,
+        peers: vec![peer],
+        sections: config.sections.clone(),
+    }
+}
+
+/*
+批量将ip.txt中的Endpoint生成wg-quick配置文件，返回(文件名, 文件内容)：
+    wg-quick使用文件名作为网卡名称(最长15个字符)，所以文件名是"前缀+序号.conf"(比如CN_1.conf)，
+    节点名称写在文件第一行的注释中；每生成一个文件调用一次progress(节点名称, 文件名)
+*/
+pub fn generate_wg_quick_files<F>(
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    cross: bool,
+    mut progress: F,
+) -> Vec<(String, String)>
+where
+    F: FnMut(&str, &str),
+{
+    let mut files = Vec::new();
+    for (index, spec) in plan_nodes(config, endpoints, cross, naming, options)
+        .iter()
+        .enumerate()
+    {
+        let node_config = render_wg_quick(config, spec.peer, &spec.endpoint, options);
+        let file_name = format!("{}.conf", interface_name(&naming.prefix, index + 1));
+        progress(&spec.name, &file_name);
+        files.push((
+            file_name,
+            format!("# {}\n{}", spec.name, node_config.to_wg_quick()),
+        ));
+    }
+    files
+}
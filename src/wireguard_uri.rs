@@ -0,0 +1,196 @@
+use crate::config::{Interface, Peer, WireGuardConfig, WARP_DEFAULT_ADDRESS};
+use crate::endpoint::Endpoint;
+use crate::error::Error;
+use crate::naming::NodeNaming;
+use crate::node::{plan_nodes, NodeOptions};
+use crate::reserved::Reserved;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+pub const WIREGUARD_URI_SCHEME: &str = "wireguard://";
+// 有些客户端导出的链接使用简写的wg://
+pub const WG_URI_SCHEME: &str = "wg://";
+
+/* 链接中的参数值需要百分号编码，比如密钥中的"+"、"/"、"="；地址中的"."、":"、","保持原样，方便阅读 */
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b',')
+    .remove(b':');
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, COMPONENT).to_string()
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().to_string()
+}
+
+/* 以WireGuard配置文件的参数为基准，生成v2rayN/Xray使用的wireguard://链接 */
+pub fn render_wireguard_uri(
+    config: &WireGuardConfig,
+    peer: &Peer,
+    endpoint: &Endpoint,
+    name: &str,
+    options: &NodeOptions,
+) -> String {
+    let mut params = vec![
+        ("publickey", encode(&peer.public_key)),
+        ("address", encode(&config.interface.addresses.join(","))),
+        ("mtu", options.mtu_for(config).to_string()),
+    ];
+    if let Some(reserved) = options.reserved_for(config, peer) {
+        params.push(("reserved", encode(&reserved.to_string())));
+    }
+    if let Some(key) = &peer.preshared_key {
+        params.push(("presharedkey", encode(key)));
+    }
+    if let Some(interval) = peer.persistent_keepalive {
+        params.push(("keepalive", interval.to_string()));
+    }
+    let query: Vec<String> = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    format!(
+        "{}{}@{}?{}#{}",
+        WIREGUARD_URI_SCHEME,
+        encode(&config.interface.private_key),
+        endpoint,
+        query.join("&"),
+        encode(name)
+    )
+}
+
+/* 批量将ip.txt中的Endpoint生成wireguard://链接，每生成一个链接调用一次progress(节点名称, 生成的内容) */
+pub fn generate_wireguard_uris<F>(
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    cross: bool,
+    mut progress: F,
+) -> Vec<String>
+where
+    F: FnMut(&str, &str),
+{
+    let mut links = Vec::new();
+    for spec in plan_nodes(config, endpoints, cross, naming, options) {
+        links.push(render_wireguard_uri(
+            config,
+            spec.peer,
+            &spec.endpoint,
+            &spec.name,
+            options,
+        ));
+        progress(&spec.name, "wireguard链接");
+    }
+    links
+}
+
+/* 判断文件内容是不是wireguard://链接，而不是wg-config.conf那样的配置文件 */
+pub fn is_wireguard_uri(contents: &str) -> bool {
+    let trimmed = contents.trim_start();
+    trimmed.starts_with(WIREGUARD_URI_SCHEME) || trimmed.starts_with(WG_URI_SCHEME)
+}
+
+/* 解析一条wireguard://链接，返回(节点名称, WireGuard配置)，链接中的主机地址和端口就是[Peer]的Endpoint */
+pub fn parse_wireguard_uri(link: &str) -> Result<(String, WireGuardConfig), Error> {
+    let link = link.trim();
+    let rest = link
+        .strip_prefix(WIREGUARD_URI_SCHEME)
+        .or_else(|| link.strip_prefix(WG_URI_SCHEME))
+        .ok_or_else(|| Error::InvalidLink(format!("不是以{}开头", WIREGUARD_URI_SCHEME)))?;
+    let (rest, name) = match rest.split_once('#') {
+        Some((rest, name)) => (rest, decode(name)),
+        None => (rest, String::new()),
+    };
+    let (authority, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (private_key, host_port) = authority
+        .rsplit_once('@')
+        .ok_or_else(|| Error::InvalidLink("缺少“私钥@主机地址:端口”部分".to_string()))?;
+    let host_port = host_port.trim_end_matches('/');
+    let endpoint = Endpoint::parse(host_port)
+        .ok_or_else(|| Error::InvalidLink(format!("主机地址和端口“{}”不合法", host_port)))?;
+
+    let mut interface = Interface {
+        private_key: decode(private_key),
+        ..Interface::default()
+    };
+    let mut peer = Peer {
+        allowed_ips: vec!["0.0.0.0/0".to_string(), "::/0".to_string()],
+        endpoint: Some(endpoint),
+        ..Peer::default()
+    };
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value);
+        let invalid = |key: &'static str| Error::InvalidValue {
+            key,
+            value: value.clone(),
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "publickey" | "public_key" | "peer_public_key" => peer.public_key = value.clone(),
+            "address" | "ip" | "local_address" => interface.addresses.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())
+                    .map(str::to_string),
+            ),
+            "mtu" => interface.mtu = Some(value.parse().map_err(|_| invalid("mtu"))?),
+            "reserved" => {
+                peer.reserved = Some(value.parse::<Reserved>().map_err(|_| invalid("reserved"))?)
+            }
+            "presharedkey" | "pre_shared_key" | "psk" => peer.preshared_key = Some(value.clone()),
+            "keepalive" | "persistentkeepalive" => {
+                peer.persistent_keepalive = Some(value.parse().map_err(|_| invalid("keepalive"))?)
+            }
+            _ => peer.extra.push((key.to_string(), value.clone())),
+        }
+    }
+    if interface.private_key.is_empty() {
+        return Err(Error::InvalidLink("缺少私钥".to_string()));
+    }
+    if peer.public_key.is_empty() {
+        return Err(Error::InvalidLink("缺少publickey参数".to_string()));
+    }
+    if interface.addresses.is_empty() {
+        interface.addresses.push(WARP_DEFAULT_ADDRESS.to_string());
+    }
+    let config = WireGuardConfig {
+        interface,
+        peers: vec![peer],
+        sections: Vec::new(),
+    };
+    Ok((name, config))
+}
+
+/* 读取一行一条的wireguard://链接，作为wg-config.conf使用：私钥和地址相同的链接合并成多个[Peer] */
+pub fn parse_wireguard_uri_list(contents: &str) -> Result<WireGuardConfig, Error> {
+    let mut merged: Option<WireGuardConfig> = None;
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (_, config) =
+            parse_wireguard_uri(line).map_err(|err| Error::parse(idx + 1, err.to_string()))?;
+        match merged.as_mut() {
+            None => merged = Some(config),
+            Some(first) => {
+                if first.interface.private_key != config.interface.private_key
+                    || first.interface.addresses != config.interface.addresses
+                {
+                    return Err(Error::parse(
+                        idx + 1,
+                        "私钥或地址跟第一个链接不同，不能合并到同一个配置中",
+                    ));
+                }
+                first.peers.extend(config.peers);
+            }
+        }
+    }
+    merged.ok_or(Error::MissingKey("wireguard://链接"))
+}
@@ -0,0 +1,173 @@
+use crate::config::{Peer, WireGuardConfig};
+use crate::endpoint::Endpoint;
+use crate::naming::NodeNaming;
+use crate::node::{plan_nodes, NodeOptions};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::str::FromStr;
+
+/* Xray的wireguard出站使用的线程数 */
+pub const XRAY_WORKERS: u16 = 2;
+
+/* 导出的Xray配置使用哪种写法：只有出站的数组(合并到已有的配置中)，或者可以直接运行的完整配置 */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum XrayLayout {
+    #[default]
+    Outbounds,
+    Standalone,
+}
+
+impl FromStr for XrayLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "outbounds" | "outbound" | "array" => Ok(XrayLayout::Outbounds),
+            "standalone" | "full" | "config" => Ok(XrayLayout::Standalone),
+            _ => Err(format!(
+                "不支持的Xray配置写法“{}”，可选的值：outbounds、standalone",
+                s.trim()
+            )),
+        }
+    }
+}
+
+/* Xray的出站，protocol为wireguard */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XrayOutbound {
+    pub tag: String,
+    pub protocol: String,
+    pub settings: XrayWireGuardSettings,
+}
+
+/* wireguard出站的settings */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XrayWireGuardSettings {
+    pub secret_key: String,
+    pub address: Vec<String>,
+    pub peers: Vec<XrayPeer>,
+    pub mtu: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved: Option<[u8; 3]>,
+    pub workers: u16,
+    pub domain_strategy: String,
+}
+
+/* wireguard出站中的peer，endpoint写成"主机地址:端口" */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XrayPeer {
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_shared_key: Option<String>,
+    pub endpoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<u16>,
+    #[serde(rename = "allowedIPs")]
+    pub allowed_ips: Vec<String>,
+}
+
+/*
+根据配置文件的Address决定domainStrategy：
+    只有IPv4地址时使用ForceIPv4，只有IPv6地址时使用ForceIPv6，两种都有时使用ForceIP
+*/
+fn domain_strategy(addresses: &[String]) -> &'static str {
+    let has_ipv6 = addresses.iter().any(|address| address.contains(':'));
+    let has_ipv4 = addresses.iter().any(|address| !address.contains(':'));
+    match (has_ipv4, has_ipv6) {
+        (true, false) => "ForceIPv4",
+        (false, true) => "ForceIPv6",
+        _ => "ForceIP",
+    }
+}
+
+impl XrayOutbound {
+    /* 以WireGuard配置文件的参数为基准，使用指定的[Peer]和Endpoint生成出站 */
+    pub fn new(
+        config: &WireGuardConfig,
+        peer: &Peer,
+        endpoint: &Endpoint,
+        tag: &str,
+        options: &NodeOptions,
+    ) -> Self {
+        let allowed_ips = if peer.allowed_ips.is_empty() {
+            vec!["0.0.0.0/0".to_string(), "::/0".to_string()]
+        } else {
+            peer.allowed_ips.clone()
+        };
+        XrayOutbound {
+            tag: tag.to_string(),
+            protocol: "wireguard".to_string(),
+            settings: XrayWireGuardSettings {
+                secret_key: config.interface.private_key.clone(),
+                address: config.interface.addresses.clone(),
+                peers: vec![XrayPeer {
+                    public_key: peer.public_key.clone(),
+                    pre_shared_key: peer.preshared_key.clone(),
+                    endpoint: endpoint.to_string(),
+                    keep_alive: peer.persistent_keepalive,
+                    allowed_ips,
+                }],
+                mtu: options.mtu_for(config),
+                reserved: options
+                    .reserved_for(config, peer)
+                    .map(|reserved| reserved.0),
+                workers: XRAY_WORKERS,
+                domain_strategy: domain_strategy(&config.interface.addresses).to_string(),
+            },
+        }
+    }
+}
+
+/*
+批量将ip.txt中的Endpoint生成Xray的wireguard出站，tag就是节点名称：
+    1、outbounds：只输出出站的数组，复制到已有配置的"outbounds"中；
+    2、standalone：输出完整的配置，本地监听127.0.0.1:10808的socks入站，默认使用第一个节点。
+每个节点调用一次progress(节点名称, 生成的内容)，由调用者决定怎么显示。
+*/
+pub fn generate_xray_config<F>(
+    endpoints: &[Endpoint],
+    naming: &NodeNaming,
+    config: &WireGuardConfig,
+    options: &NodeOptions,
+    cross: bool,
+    layout: XrayLayout,
+    mut progress: F,
+) -> Option<String>
+where
+    F: FnMut(&str, &str),
+{
+    let specs = plan_nodes(config, endpoints, cross, naming, options);
+    if specs.is_empty() {
+        return None;
+    }
+    let outbounds: Vec<XrayOutbound> = specs
+        .iter()
+        .map(|spec| XrayOutbound::new(config, spec.peer, &spec.endpoint, &spec.name, options))
+        .collect();
+    let document = match layout {
+        XrayLayout::Outbounds => json!(outbounds),
+        XrayLayout::Standalone => {
+            let mut all_outbounds = json!(outbounds);
+            if let Some(list) = all_outbounds.as_array_mut() {
+                list.push(json!({ "tag": "direct", "protocol": "freedom" }));
+            }
+            json!({
+                "log": { "loglevel": "warning" },
+                "inbounds": [{
+                    "tag": "socks-in",
+                    "listen": "127.0.0.1",
+                    "port": 10808,
+                    "protocol": "socks",
+                    "settings": { "udp": true }
+                }],
+                "outbounds": all_outbounds
+            })
+        }
+    };
+    for spec in &specs {
+        progress(&spec.name, "Xray出站");
+    }
+    Some(serde_json::to_string_pretty(&document).expect("无法序列化Xray配置"))
+}